@@ -73,16 +73,14 @@ async fn main(_spawner: Spawner) {
         match event {
             Event::Key(KeyEvent {
                 code: KeyCode::Char(c),
-                modifiers: _,
-                kind: _,
+                ..
             }) => {
                 uart.execute(Print(c)).expect("write char");
             }
 
             Event::Key(KeyEvent {
                 code: KeyCode::Enter,
-                modifiers: _,
-                kind: _,
+                ..
             }) => {
                 uart.execute(MoveToNextLine(1)).expect("moved");
             }
@@ -2,6 +2,7 @@
 
 use core::fmt;
 
+use crate::terminal::TerminalCapabilities;
 use crate::{Command, csi};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -192,4 +193,15 @@ impl Command for SetCursorStyle {
     fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
         write!(writer, "{}", self.0)
     }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if capabilities.cursor_style {
+            self.write(writer)?;
+        }
+        Ok(())
+    }
 }
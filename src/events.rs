@@ -2,21 +2,74 @@
 
 use bitflags::bitflags;
 
+use crate::style::Color;
+
+pub mod decoder;
 pub mod parser;
+pub mod reader;
 pub mod stream;
 
-pub use parser::parse;
-pub use stream::stream;
+pub use decoder::{DecoderConfig, EventDecoder, OverflowPolicy};
+pub use parser::{ParseError, ParserConfig, parse, parse_bytes, parse_bytes_with};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub use crate::io::PARSE_ERROR_CAPACITY;
+pub use reader::{EventReader, read};
+pub use stream::{
+    AsyncEventReader, StreamConfig, stream, stream_with_config, stream_with_escape_timeout,
+};
+
+/// Maximum size in bytes of the content of a single paste event.
+pub const PASTE_CAPACITY: usize = 64;
+
+/// Maximum size in bytes of the text associated to a key event.
+pub const KEY_TEXT_CAPACITY: usize = 16;
+
+/// Maximum size in bytes of the raw content kept by an unknown event.
+pub const UNKNOWN_CAPACITY: usize = 32;
+
+/// Maximum number of attributes kept by a primary device attributes reply.
+pub const DEVICE_ATTRIBUTES_CAPACITY: usize = 16;
+
+/// Maximum size in bytes of the name and version reported by a terminal.
+pub const TERMINAL_VERSION_CAPACITY: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
+    /// Reply to a color query.
+    Color(ColorEvent),
     Cursor(CursorEvent),
     Key(KeyEvent),
+    /// Reply to [`crate::keyboard::QueryKeyboardEnhancementFlags`].
+    KeyboardEnhancementFlags(KeyboardEnhancementFlags),
+    /// Reply to [`crate::terminal::RequestMode`].
+    Mode(ModeReport),
+    Mouse(MouseEvent),
+    Paste(PasteEvent),
     Screen(ScreenEvent),
+    /// Reply to a terminal identification query.
+    Terminal(TerminalResponse),
+    /// Control sequence that is well-formed but not understood, e.g. vendor-specific replies.
+    Unknown(UnknownEvent),
+    /// Events lost because the queue of pending events was full.
+    Overflow {
+        dropped: usize,
+    },
 }
 
 impl Event {
+    pub fn is_color_event(&self) -> bool {
+        matches!(self, Event::Color(_))
+    }
+
+    pub fn as_color_event(&self) -> Option<&ColorEvent> {
+        if let Event::Color(event) = &self {
+            return Some(event);
+        }
+
+        None
+    }
+
     pub fn is_screen_event(&self) -> bool {
         matches!(self, Event::Screen(_))
     }
@@ -40,6 +93,92 @@ impl Event {
 
         None
     }
+
+    pub fn is_mouse_event(&self) -> bool {
+        matches!(self, Event::Mouse(_))
+    }
+
+    pub fn as_mouse_event(&self) -> Option<&MouseEvent> {
+        if let Event::Mouse(event) = &self {
+            return Some(event);
+        }
+
+        None
+    }
+
+    pub fn is_mode_event(&self) -> bool {
+        matches!(self, Event::Mode(_))
+    }
+
+    pub fn as_mode_event(&self) -> Option<&ModeReport> {
+        if let Event::Mode(event) = &self {
+            return Some(event);
+        }
+
+        None
+    }
+
+    pub fn is_paste_event(&self) -> bool {
+        matches!(self, Event::Paste(_))
+    }
+
+    pub fn as_paste_event(&self) -> Option<&PasteEvent> {
+        if let Event::Paste(event) = &self {
+            return Some(event);
+        }
+
+        None
+    }
+
+    pub fn is_terminal_event(&self) -> bool {
+        matches!(self, Event::Terminal(_))
+    }
+
+    pub fn as_terminal_event(&self) -> Option<&TerminalResponse> {
+        if let Event::Terminal(event) = &self {
+            return Some(event);
+        }
+
+        None
+    }
+
+    pub fn is_overflow_event(&self) -> bool {
+        matches!(self, Event::Overflow { .. })
+    }
+
+    pub fn is_unknown_event(&self) -> bool {
+        matches!(self, Event::Unknown(_))
+    }
+
+    pub fn as_unknown_event(&self) -> Option<&UnknownEvent> {
+        if let Event::Unknown(event) = &self {
+            return Some(event);
+        }
+
+        None
+    }
+}
+
+/// Color reported by the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ColorEvent {
+    /// Reply to [`crate::terminal::QueryForegroundColor`].
+    Foreground(Color),
+    /// Reply to [`crate::terminal::QueryBackgroundColor`].
+    Background(Color),
+    /// Reply to [`crate::terminal::QueryPaletteColor`].
+    Palette { index: u8, color: Color },
+}
+
+impl ColorEvent {
+    pub fn color(&self) -> Color {
+        match *self {
+            ColorEvent::Foreground(color)
+            | ColorEvent::Background(color)
+            | ColorEvent::Palette { color, .. } => color,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -48,12 +187,19 @@ pub enum CursorEvent {
     Positioned { x: u16, y: u16 },
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct KeyEvent {
     pub code: KeyCode,
     pub modifiers: KeyModifiers,
     pub kind: KeyEventKind,
+    /// Key produced with the shift modifier, if reported by the terminal.
+    pub shifted_key: Option<KeyCode>,
+    /// Key at the same physical location on a standard PC-101 (US) layout, if reported by the
+    /// terminal. It allows keybindings not to depend on the active keyboard layout.
+    pub base_layout_key: Option<KeyCode>,
+    /// Text generated by the key, if reported by the terminal.
+    pub text: heapless::String<KEY_TEXT_CAPACITY>,
 }
 
 impl KeyEvent {
@@ -62,6 +208,9 @@ impl KeyEvent {
             code,
             modifiers: KeyModifiers::empty(),
             kind: KeyEventKind::Pressed,
+            shifted_key: None,
+            base_layout_key: None,
+            text: heapless::String::new(),
         }
     }
 
@@ -89,6 +238,27 @@ impl KeyEvent {
         self
     }
 
+    pub fn with_alternate_keys(
+        mut self,
+        shifted_key: Option<KeyCode>,
+        base_layout_key: Option<KeyCode>,
+    ) -> Self {
+        self.shifted_key = shifted_key;
+        self.base_layout_key = base_layout_key;
+        self
+    }
+
+    /// Set the associated text, truncated to [`KEY_TEXT_CAPACITY`] bytes.
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text.clear();
+        for c in text.chars() {
+            if self.text.push(c).is_err() {
+                break;
+            }
+        }
+        self
+    }
+
     pub fn sanitize(mut self) -> Self {
         if self.code == KeyCode::Tab && self.modifiers.contains(KeyModifiers::SHIFT) {
             self.code = KeyCode::BackTab;
@@ -103,9 +273,8 @@ impl KeyEvent {
 
     pub fn as_key_pressed(&self) -> Option<&KeyEvent> {
         if let KeyEvent {
-            code: _,
-            modifiers: _,
             kind: KeyEventKind::Pressed,
+            ..
         } = &self
         {
             return Some(self);
@@ -120,9 +289,8 @@ impl KeyEvent {
 
     pub fn as_key_released(&self) -> Option<&KeyEvent> {
         if let KeyEvent {
-            code: _,
-            modifiers: _,
             kind: KeyEventKind::Released,
+            ..
         } = &self
         {
             return Some(self);
@@ -137,9 +305,8 @@ impl KeyEvent {
 
     pub fn as_key_repeated(&self) -> Option<&KeyEvent> {
         if let KeyEvent {
-            code: _,
-            modifiers: _,
             kind: KeyEventKind::Repeated,
+            ..
         } = &self
         {
             return Some(self);
@@ -169,6 +336,14 @@ pub enum KeyCode {
     Fn(u8),
     Char(char),
     Escape,
+    CapsLock,
+    ScrollLock,
+    NumLock,
+    PrintScreen,
+    Pause,
+    Menu,
+    Keypad(KeypadKeyCode),
+    Media(MediaKeyCode),
     Modifier(ModifierKeyCode),
 }
 
@@ -197,6 +372,30 @@ impl KeyCode {
         None
     }
 
+    pub fn is_keypad_key(&self) -> bool {
+        matches!(self, KeyCode::Keypad(_))
+    }
+
+    pub fn as_keypad_key(&self) -> Option<&KeyCode> {
+        if let KeyCode::Keypad(_) = &self {
+            return Some(self);
+        }
+
+        None
+    }
+
+    pub fn is_media_key(&self) -> bool {
+        matches!(self, KeyCode::Media(_))
+    }
+
+    pub fn as_media_key(&self) -> Option<&KeyCode> {
+        if let KeyCode::Media(_) = &self {
+            return Some(self);
+        }
+
+        None
+    }
+
     pub fn is_char_key(&self) -> bool {
         matches!(self, KeyCode::Char(_))
     }
@@ -212,11 +411,7 @@ impl KeyCode {
 
 impl From<KeyCode> for KeyEvent {
     fn from(code: KeyCode) -> Self {
-        KeyEvent {
-            code,
-            modifiers: KeyModifiers::empty(),
-            kind: KeyEventKind::Pressed,
-        }
+        KeyEvent::new(code)
     }
 }
 
@@ -269,21 +464,77 @@ pub enum ModifierKeyCode {
     LeftControl,
     LeftAlt,
     LeftSuper,
+    LeftHyper,
     LeftMeta,
     RightShift,
     RightControl,
     RightAlt,
     RightSuper,
+    RightHyper,
     RightMeta,
+    IsoLevel3Shift,
+    IsoLevel5Shift,
 }
 
 impl From<ModifierKeyCode> for KeyEvent {
     fn from(code: ModifierKeyCode) -> Self {
-        KeyEvent {
-            code: KeyCode::Modifier(code),
-            modifiers: KeyModifiers::empty(),
-            kind: KeyEventKind::Pressed,
-        }
+        KeyEvent::new(KeyCode::Modifier(code))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum KeypadKeyCode {
+    /// Digit from 0 to 9.
+    Digit(u8),
+    Decimal,
+    Divide,
+    Multiply,
+    Subtract,
+    Add,
+    Enter,
+    Equal,
+    Separator,
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Insert,
+    Delete,
+    Begin,
+}
+
+impl From<KeypadKeyCode> for KeyEvent {
+    fn from(code: KeypadKeyCode) -> Self {
+        KeyEvent::new(KeyCode::Keypad(code))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MediaKeyCode {
+    Play,
+    Pause,
+    PlayPause,
+    Reverse,
+    Stop,
+    FastForward,
+    Rewind,
+    TrackNext,
+    TrackPrevious,
+    Record,
+    LowerVolume,
+    RaiseVolume,
+    MuteVolume,
+}
+
+impl From<MediaKeyCode> for KeyEvent {
+    fn from(code: MediaKeyCode) -> Self {
+        KeyEvent::new(KeyCode::Media(code))
     }
 }
 
@@ -297,7 +548,27 @@ bitflags! {
         const CONTROL = 1 << 1;
         const ALT = 1 << 2;
         const SUPER = 1 << 3;
+        const HYPER = 1 << 4;
         const META = 1 << 5;
+        /// Caps lock is active, not a modifier key being held.
+        const CAPS_LOCK = 1 << 6;
+        /// Num lock is active, not a modifier key being held.
+        const NUM_LOCK = 1 << 7;
+    }
+}
+
+/// Kitty keyboard protocol progressive enhancement flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct KeyboardEnhancementFlags(u8);
+
+bitflags! {
+    impl KeyboardEnhancementFlags: u8 {
+        const DISAMBIGUATE_ESCAPE_CODES = 1 << 0;
+        const REPORT_EVENT_TYPES = 1 << 1;
+        const REPORT_ALTERNATE_KEYS = 1 << 2;
+        const REPORT_ALL_KEYS_AS_ESCAPE_CODES = 1 << 3;
+        const REPORT_ASSOCIATED_TEXT = 1 << 4;
     }
 }
 
@@ -309,12 +580,181 @@ pub enum KeyEventKind {
     Repeated,
 }
 
+/// Mouse event.
+///
+/// Columns and rows are zero-based, as expected by [`crate::cursor::MoveTo`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    pub column: u16,
+    pub row: u16,
+    pub modifiers: KeyModifiers,
+}
+
+impl MouseEvent {
+    pub fn new(kind: MouseEventKind, column: u16, row: u16) -> Self {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::empty(),
+        }
+    }
+
+    pub fn with_modifiers(mut self, modifiers: KeyModifiers) -> Self {
+        self.modifiers |= modifiers;
+        self
+    }
+
+    pub fn button(&self) -> Option<MouseButton> {
+        match self.kind {
+            MouseEventKind::Down(button)
+            | MouseEventKind::Up(button)
+            | MouseEventKind::Drag(button) => Some(button),
+            _ => None,
+        }
+    }
+
+    pub fn is_scroll(&self) -> bool {
+        matches!(
+            self.kind,
+            MouseEventKind::ScrollDown
+                | MouseEventKind::ScrollUp
+                | MouseEventKind::ScrollLeft
+                | MouseEventKind::ScrollRight
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MouseEventKind {
+    Down(MouseButton),
+    Up(MouseButton),
+    Drag(MouseButton),
+    Moved,
+    ScrollDown,
+    ScrollUp,
+    ScrollLeft,
+    ScrollRight,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// State of a terminal mode, i.e. `CSI ? mode ; state $ y` for a DEC private mode or
+/// `CSI mode ; state $ y` for an ANSI mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ModeReport {
+    pub mode: u16,
+    pub private: bool,
+    pub state: ModeState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ModeState {
+    NotRecognized,
+    Set,
+    Reset,
+    PermanentlySet,
+    PermanentlyReset,
+}
+
+impl ModeState {
+    /// Tell whether the terminal knows the mode, whatever its state.
+    pub fn is_recognized(&self) -> bool {
+        *self != ModeState::NotRecognized
+    }
+
+    pub fn is_set(&self) -> bool {
+        matches!(self, ModeState::Set | ModeState::PermanentlySet)
+    }
+
+    pub fn is_reset(&self) -> bool {
+        matches!(self, ModeState::Reset | ModeState::PermanentlyReset)
+    }
+
+    /// Tell whether the state of the mode may not be changed.
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            ModeState::PermanentlySet | ModeState::PermanentlyReset
+        )
+    }
+}
+
+/// Bracketed paste event.
+///
+/// A paste whose content does not fit into [`PASTE_CAPACITY`] bytes is split into several
+/// consecutive events, all of them but the last one being flagged as partial.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PasteEvent {
+    pub content: heapless::String<PASTE_CAPACITY>,
+    pub partial: bool,
+}
+
+impl PasteEvent {
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.content.as_str()
+    }
+}
+
+/// Raw bytes of an unknown control sequence.
+///
+/// Only the first [`UNKNOWN_CAPACITY`] bytes are kept, in which case the event is flagged as
+/// truncated.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UnknownEvent {
+    pub bytes: heapless::Vec<u8, UNKNOWN_CAPACITY>,
+    pub truncated: bool,
+}
+
+impl UnknownEvent {
+    pub fn new(bytes: &[u8]) -> Self {
+        let len = bytes.len().min(UNKNOWN_CAPACITY);
+
+        Self {
+            bytes: heapless::Vec::from_slice(&bytes[..len]).unwrap_or_default(),
+            truncated: len < bytes.len(),
+        }
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ScreenEvent {
     FocusGained,
     FocusLost,
-    Resized { width: u16, height: u16 },
+    /// Size of the text area in characters, along with its size in pixels, which is zero when
+    /// unknown.
+    Resized {
+        width: u16,
+        height: u16,
+        pixel_width: u16,
+        pixel_height: u16,
+    },
 }
 
 impl ScreenEvent {
@@ -327,24 +767,58 @@ impl ScreenEvent {
     }
 
     pub fn is_resized(&self) -> bool {
-        matches!(
-            self,
-            ScreenEvent::Resized {
-                width: _,
-                height: _
-            }
-        )
+        matches!(self, ScreenEvent::Resized { .. })
     }
 
     pub fn as_resized(&self) -> Option<&ScreenEvent> {
-        if let ScreenEvent::Resized {
-            width: _,
-            height: _,
-        } = &self
-        {
+        if let ScreenEvent::Resized { .. } = &self {
             return Some(self);
         }
 
         None
     }
 }
+
+/// Reply to a terminal identification query.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TerminalResponse {
+    /// Reply to [`crate::terminal::RequestPrimaryDeviceAttributes`], i.e. `CSI ? class ; attributes
+    /// c`, e.g. `62` for a VT220-class terminal with `22` for ANSI colors among its attributes.
+    ///
+    /// Only the first [`DEVICE_ATTRIBUTES_CAPACITY`] attributes are kept.
+    PrimaryDeviceAttributes {
+        class: u16,
+        attributes: heapless::Vec<u16, DEVICE_ATTRIBUTES_CAPACITY>,
+    },
+    /// Reply to [`crate::terminal::RequestSecondaryDeviceAttributes`], i.e. `CSI > type ; version
+    /// ; cartridge c`. The cartridge number is zero when omitted.
+    SecondaryDeviceAttributes {
+        terminal_type: u16,
+        version: u16,
+        cartridge: u16,
+    },
+    /// Reply to [`crate::terminal::RequestTerminalVersion`], i.e. `DCS > | text ST`, e.g.
+    /// `XTerm(390)` or `kitty(0.35.2)`.
+    ///
+    /// Only the first [`TERMINAL_VERSION_CAPACITY`] bytes are kept.
+    Version(heapless::String<TERMINAL_VERSION_CAPACITY>),
+}
+
+impl TerminalResponse {
+    pub fn is_primary_device_attributes(&self) -> bool {
+        matches!(self, TerminalResponse::PrimaryDeviceAttributes { .. })
+    }
+
+    pub fn is_secondary_device_attributes(&self) -> bool {
+        matches!(self, TerminalResponse::SecondaryDeviceAttributes { .. })
+    }
+
+    pub fn as_version(&self) -> Option<&str> {
+        if let TerminalResponse::Version(version) = &self {
+            return Some(version.as_str());
+        }
+
+        None
+    }
+}
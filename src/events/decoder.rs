@@ -0,0 +1,579 @@
+//! Event decoder.
+
+use heapless::Deque;
+
+use crate::events::parser::SequenceTail;
+use crate::events::{Event, KeyCode, ParseError, ParserConfig, PasteEvent, UnknownEvent, parser};
+use crate::io;
+
+#[cfg(test)]
+mod tests;
+
+/// Default size in bytes of the input buffer of a decoder.
+///
+/// It holds the replies to [`probe`], e.g. the primary device attributes of xterm which are
+/// about 40 bytes long.
+///
+/// [`probe`]: crate::terminal::capabilities::probe
+pub const DEFAULT_BUFFER_SIZE: usize = 64;
+
+/// Default maximum number of pending events of a decoder.
+pub const DEFAULT_QUEUE_SIZE: usize = 32;
+
+/// Minimum size in bytes of the input buffer of a decoder.
+pub const MIN_BUFFER_SIZE: usize = 16;
+
+/// Behavior of a decoder whose queue of pending events is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OverflowPolicy {
+    /// Drop the oldest pending events, then report [`Event::Overflow`] before the remaining ones.
+    #[default]
+    DropOldest,
+    /// Drop the newest events, then report [`Event::Overflow`] after the pending ones.
+    DropNewest,
+    /// Stop parsing the input until pending events are consumed, so that no event is lost as long
+    /// as the reader buffers its own input.
+    Backpressure,
+}
+
+/// Decoder config.
+///
+/// The input buffer must hold the longest sequence to be parsed, otherwise its first bytes are
+/// reported as a truncated [`Event::Unknown`] and the rest is skipped up to the end of the
+/// sequence. It must be at least [`MIN_BUFFER_SIZE`] bytes long, and the default size holds the
+/// replies to terminal queries. The queue holds the events parsed from a single read, see
+/// [`OverflowPolicy`] when it is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DecoderConfig<
+    const BUFFER_SIZE: usize = DEFAULT_BUFFER_SIZE,
+    const QUEUE_SIZE: usize = DEFAULT_QUEUE_SIZE,
+> {
+    parser: ParserConfig,
+    overflow_policy: OverflowPolicy,
+}
+
+impl DecoderConfig {
+    /// Create a config with default buffer and queue sizes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<const BUFFER_SIZE: usize, const QUEUE_SIZE: usize> DecoderConfig<BUFFER_SIZE, QUEUE_SIZE> {
+    /// Set the size in bytes of the input buffer.
+    pub fn with_buffer_size<const SIZE: usize>(self) -> DecoderConfig<SIZE, QUEUE_SIZE> {
+        DecoderConfig {
+            parser: self.parser,
+            overflow_policy: self.overflow_policy,
+        }
+    }
+
+    /// Set the maximum number of pending events.
+    pub fn with_queue_size<const SIZE: usize>(self) -> DecoderConfig<BUFFER_SIZE, SIZE> {
+        DecoderConfig {
+            parser: self.parser,
+            overflow_policy: self.overflow_policy,
+        }
+    }
+
+    /// Parse events with the given parser config.
+    pub fn with_parser_config(mut self, parser: ParserConfig) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Set the behavior when the queue of pending events is full.
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    pub fn parser_config(&self) -> &ParserConfig {
+        &self.parser
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+}
+
+/// Queue of pending events that keeps track of the dropped ones.
+#[derive(Debug)]
+pub(crate) struct EventQueue<const N: usize> {
+    events: Deque<io::Result<Event>, N>,
+    policy: OverflowPolicy,
+    dropped: usize,
+}
+
+impl<const N: usize> EventQueue<N> {
+    pub(crate) fn new(policy: OverflowPolicy) -> Self {
+        Self {
+            events: Deque::new(),
+            policy,
+            dropped: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.events.clear();
+        self.dropped = 0;
+    }
+
+    /// Tell whether parsing must stop until pending events are consumed.
+    pub(crate) fn is_blocked(&self) -> bool {
+        self.policy == OverflowPolicy::Backpressure && self.events.is_full()
+    }
+
+    pub(crate) fn push(&mut self, item: io::Result<Event>) {
+        if self.events.is_full() {
+            self.dropped += 1;
+
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    self.events.pop_front();
+                }
+                OverflowPolicy::DropNewest | OverflowPolicy::Backpressure => return,
+            }
+        }
+
+        // SAFETY: we can ensure that the queue is not full thanks to the lines above.
+        unsafe { self.events.push_back_unchecked(item) }
+    }
+
+    /// Pop the next event, reporting dropped events in place of the missing ones.
+    pub(crate) fn pop(&mut self) -> Option<io::Result<Event>> {
+        if self.dropped > 0 && (self.policy == OverflowPolicy::DropOldest || self.events.is_empty())
+        {
+            let dropped = core::mem::take(&mut self.dropped);
+            return Some(Ok(Event::Overflow { dropped }));
+        }
+
+        self.events.pop_front()
+    }
+}
+
+/// Push-style decoder of events from raw input, that holds the pending input and the parsed
+/// events.
+///
+/// It is the core shared by event streams and blocking event readers, and it may be used on its
+/// own when bytes are received by other means, e.g. from an interrupt handler. Its sizes and
+/// behavior are set by a [`DecoderConfig`], while the escape timeout is left to the caller, see
+/// [`EventDecoder::flush_escape`].
+#[derive(Debug)]
+pub struct EventDecoder<
+    const BUFFER_SIZE: usize = DEFAULT_BUFFER_SIZE,
+    const QUEUE_SIZE: usize = DEFAULT_QUEUE_SIZE,
+> {
+    parser: ParserConfig,
+    buffer: [u8; BUFFER_SIZE],
+    rpos: usize,
+    wpos: usize,
+    queue: EventQueue<QUEUE_SIZE>,
+    paste: Option<PasteEvent>,
+    skipped: Option<SequenceTail>,
+    stalled: bool,
+}
+
+impl EventDecoder {
+    /// Create a decoder with default config.
+    pub fn new() -> Self {
+        Self::with_config(DecoderConfig::new())
+    }
+}
+
+impl Default for EventDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const BUFFER_SIZE: usize, const QUEUE_SIZE: usize> EventDecoder<BUFFER_SIZE, QUEUE_SIZE> {
+    /// Create a decoder with the given config.
+    pub fn with_config(config: DecoderConfig<BUFFER_SIZE, QUEUE_SIZE>) -> Self {
+        const {
+            assert!(
+                BUFFER_SIZE >= MIN_BUFFER_SIZE,
+                "decoder buffer is too small"
+            );
+            assert!(QUEUE_SIZE > 0, "decoder queue must not be empty");
+        }
+
+        EventDecoder {
+            parser: *config.parser_config(),
+            buffer: [0; BUFFER_SIZE],
+            rpos: 0,
+            wpos: 0,
+            queue: EventQueue::new(config.overflow_policy()),
+            paste: None,
+            skipped: None,
+            stalled: false,
+        }
+    }
+
+    /// Drop the pending input and events, e.g. after a read error.
+    pub fn reset(&mut self) {
+        self.rpos = 0;
+        self.wpos = 0;
+        self.queue.clear();
+        self.paste = None;
+        self.skipped = None;
+        self.stalled = false;
+    }
+
+    /// Feed raw input and return the number of accepted bytes.
+    ///
+    /// All bytes are accepted unless the queue of pending events is full with the
+    /// [`OverflowPolicy::Backpressure`] policy, in which case remaining bytes must be fed again
+    /// once some events are consumed.
+    pub fn feed(&mut self, mut data: &[u8]) -> usize {
+        let total = data.len();
+
+        while !data.is_empty() {
+            self.compact();
+
+            if self.wpos == BUFFER_SIZE {
+                if self.stalled {
+                    break;
+                }
+
+                self.overflow();
+                continue;
+            }
+
+            let count = (BUFFER_SIZE - self.wpos).min(data.len());
+            self.buffer[self.wpos..self.wpos + count].copy_from_slice(&data[..count]);
+            self.commit(count);
+
+            data = &data[count..];
+        }
+
+        total - data.len()
+    }
+
+    /// Pop the next event, if any, or the error on input that can never be parsed.
+    ///
+    /// Once it returns `None`, the free tail of the buffer is guaranteed not to be empty.
+    pub fn next_event(&mut self) -> Option<io::Result<Event>> {
+        loop {
+            // We start by purging the queue of pending events in order to preserve causality.
+            if let Some(event) = self.queue.pop() {
+                return Some(event);
+            }
+
+            // Resume parsing the pending input now that the queue is drained.
+            if self.stalled {
+                self.stalled = false;
+                self.process();
+                continue;
+            }
+
+            self.compact();
+
+            if self.wpos == BUFFER_SIZE {
+                self.overflow();
+                continue;
+            }
+
+            return None;
+        }
+    }
+
+    /// Get the free tail of the buffer to read input into.
+    pub(crate) fn spare_mut(&mut self) -> &mut [u8] {
+        self.compact();
+        &mut self.buffer[self.wpos..]
+    }
+
+    /// Parse the given number of bytes that have just been read into the free tail of the buffer.
+    pub(crate) fn commit(&mut self, count: usize) {
+        self.wpos = (self.wpos + count).min(BUFFER_SIZE);
+        self.process();
+    }
+
+    /// Report the pending escape byte on its own, e.g. once no continuation byte arrived in time.
+    pub fn flush_escape(&mut self) {
+        if self.is_escape_pending() {
+            self.rpos += 1;
+            self.queue.push(Ok(Event::Key(KeyCode::Escape.into())));
+            self.process();
+        }
+    }
+
+    /// Report the pending input on its own once no more input will arrive, e.g. at the end of a
+    /// file, and tell whether there was any.
+    ///
+    /// A lone escape byte is reported as [`KeyCode::Escape`], an unterminated paste as a partial
+    /// paste event, and an incomplete sequence as a truncated [`Event::Unknown`], or as a
+    /// [`ParseError`] if it is a partial UTF-8 char. A single event is reported per call, so that
+    /// it must be called again once the pending events are consumed.
+    pub fn finish(&mut self) -> bool {
+        let pending = &self.buffer[self.rpos..self.wpos];
+
+        if let Some(paste) = self.paste.take() {
+            // A partial end marker is dropped, and a partial char is reported on the next call.
+            self.wpos -= parser::partial_bracketed_paste_end_len(pending, &self.parser);
+
+            self.queue.push(Ok(Event::Paste(PasteEvent {
+                partial: true,
+                ..paste
+            })));
+            return true;
+        }
+
+        // The rest of a truncated sequence is dropped along with it.
+        if self.skipped.take().is_some() || pending.is_empty() {
+            self.rpos = 0;
+            self.wpos = 0;
+            return false;
+        }
+
+        if self.is_escape_pending() {
+            self.flush_escape();
+            return true;
+        }
+
+        let partial_char = matches!(
+            str::from_utf8(pending),
+            Err(err) if err.valid_up_to() == 0 && err.error_len().is_none()
+        );
+
+        let event = if partial_char {
+            Err(ParseError::new(pending).into())
+        } else {
+            Ok(Event::Unknown(UnknownEvent {
+                truncated: true,
+                ..UnknownEvent::new(pending)
+            }))
+        };
+        self.queue.push(event);
+
+        self.rpos = 0;
+        self.wpos = 0;
+        true
+    }
+
+    /// Move the pending input to the beginning of the buffer in order to read as much as possible
+    /// into its free tail.
+    fn compact(&mut self) {
+        if self.rpos > 0 {
+            self.buffer.copy_within(self.rpos..self.wpos, 0);
+            self.wpos -= self.rpos;
+            self.rpos = 0;
+        }
+    }
+
+    /// Report the pending input as a truncated unknown event once the buffer is full without
+    /// having been able to parse anything, e.g. on a sequence longer than the buffer, then skip the
+    /// rest of the sequence.
+    fn overflow(&mut self) {
+        let event = UnknownEvent {
+            truncated: true,
+            ..UnknownEvent::new(&self.buffer[self.rpos..self.wpos])
+        };
+
+        self.queue.push(Ok(Event::Unknown(event)));
+        self.skipped = SequenceTail::of(&self.buffer[self.rpos..self.wpos], &self.parser);
+
+        self.rpos = 0;
+        self.wpos = 0;
+    }
+
+    /// Tell whether the pending input is a lone escape byte still waiting for its continuation.
+    ///
+    /// A sequence whose introducer already arrived is never pending, so that it is not split into
+    /// keys when the rest of it arrives late, e.g. on a slow serial line.
+    pub fn is_escape_pending(&self) -> bool {
+        self.paste.is_none()
+            && self.skipped.is_none()
+            && self.buffer[self.rpos..self.wpos] == [b'\x1b']
+    }
+
+    /// Parse as many events as possible from the pending input.
+    fn process(&mut self) {
+        let mut input = &self.buffer[self.rpos..self.wpos];
+
+        loop {
+            if input.is_empty() {
+                self.rpos = 0;
+                self.wpos = 0;
+                break;
+            }
+
+            if self.queue.is_blocked() {
+                self.stalled = true;
+                break;
+            }
+
+            // The rest of a truncated sequence is dropped until its end shows up, so that it is not
+            // taken for other input.
+            if let Some(tail) = self.skipped {
+                let (count, end) = tail.skip(input);
+
+                self.rpos += count;
+                input = &input[count..];
+
+                if end {
+                    self.skipped = None;
+                } else if !input.is_empty() {
+                    break;
+                }
+
+                continue;
+            }
+
+            // Pasted content is accumulated as is, without interpretation, until the end marker
+            // shows up.
+            if let Some(paste) = self.paste.as_mut() {
+                let (content, marker) = match parser::find_bracketed_paste_end(input, &self.parser)
+                {
+                    Some((end, len)) => (&input[..end], len),
+                    None => {
+                        let partial = parser::partial_bracketed_paste_end_len(input, &self.parser);
+                        (&input[..input.len() - partial], 0)
+                    }
+                };
+
+                let consumed = push_paste_content(paste, content, marker > 0, &mut self.queue);
+
+                self.rpos += consumed;
+                input = &input[consumed..];
+
+                // Either a partial char waiting for its remaining bytes, or a full queue.
+                if consumed < content.len() {
+                    self.stalled = self.queue.is_blocked();
+                    break;
+                }
+
+                if marker > 0 {
+                    if self.queue.is_blocked() {
+                        self.stalled = true;
+                        break;
+                    }
+
+                    self.rpos += marker;
+                    input = &input[marker..];
+
+                    if let Some(paste) = self.paste.take() {
+                        self.queue.push(Ok(Event::Paste(paste)));
+                    }
+                } else if !input.is_empty() {
+                    break;
+                }
+
+                continue;
+            }
+
+            if self.parser.accepts(input)
+                && let Ok((rest, _)) = parser::parse_bracketed_paste_start(input)
+            {
+                self.rpos += input.len() - rest.len();
+                input = rest;
+                self.paste = Some(PasteEvent::default());
+                continue;
+            }
+
+            match parser::parse_bytes_with(input, &self.parser) {
+                Ok((rest, event)) => {
+                    self.rpos += input.len() - rest.len();
+                    input = rest;
+
+                    self.queue.push(Ok(event));
+                }
+
+                Err(nom::Err::Incomplete(_)) => break,
+
+                // Input that no parser recognizes, which should not happen as unknown sequences are
+                // caught on their own, then drop its first byte in order to never stall.
+                Err(nom::Err::Error(_)) => {
+                    self.queue
+                        .push(Ok(Event::Unknown(UnknownEvent::new(&input[..1]))));
+
+                    self.rpos += 1;
+                    input = &input[1..];
+                }
+
+                // Invalid input, then drop the offending bytes only in order to preserve the
+                // surrounding input.
+                Err(nom::Err::Failure(err)) => {
+                    let offset = input.len() - err.input.len();
+                    let count = offset + parser::invalid_utf8_len(err.input).unwrap_or(1);
+
+                    self.queue
+                        .push(Err(ParseError::new(&input[offset..count]).into()));
+
+                    self.rpos += count;
+                    input = &input[count..];
+                }
+            }
+        }
+    }
+}
+
+/// Push pasted content, splitting the paste event when full, and return the number of consumed
+/// bytes.
+///
+/// Invalid UTF-8 sequences are replaced by U+FFFD. A partial char at the end of the content is
+/// left unconsumed, unless the content is complete. Content is left unconsumed as well once the
+/// queue is blocked.
+fn push_paste_content<const N: usize>(
+    paste: &mut PasteEvent,
+    content: &[u8],
+    complete: bool,
+    queue: &mut EventQueue<N>,
+) -> usize {
+    let mut push = |c: char| {
+        if paste.content.push(c).is_err() {
+            if queue.is_blocked() {
+                return false;
+            }
+
+            let chunk = PasteEvent {
+                content: core::mem::take(&mut paste.content),
+                partial: true,
+            };
+            queue.push(Ok(Event::Paste(chunk)));
+
+            // A single char always fits into an empty paste event.
+            let _ = paste.content.push(c);
+        }
+
+        true
+    };
+
+    let mut rest = content;
+
+    while !rest.is_empty() {
+        let (valid, invalid) = match str::from_utf8(rest) {
+            Ok(valid) => (valid, 0),
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+                let invalid = match err.error_len() {
+                    Some(len) => len,
+                    None if complete => invalid.len(),
+                    None => 0,
+                };
+                (str::from_utf8(valid).unwrap_or_default(), invalid)
+            }
+        };
+
+        for (i, c) in valid.char_indices() {
+            if !push(c) {
+                return content.len() - rest.len() + i;
+            }
+        }
+
+        if invalid > 0 && !push(char::REPLACEMENT_CHARACTER) {
+            return content.len() - rest.len() + valid.len();
+        }
+
+        rest = &rest[valid.len() + invalid..];
+
+        if 0 == invalid {
+            break;
+        }
+    }
+
+    content.len() - rest.len()
+}
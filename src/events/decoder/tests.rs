@@ -0,0 +1,177 @@
+use rstest::rstest;
+use speculoos::prelude::*;
+
+use crate::events::{
+    DecoderConfig, Event, EventDecoder, KeyCode, KeyEvent, OverflowPolicy, ParseError,
+    ParserConfig, UnknownEvent, parse_bytes,
+};
+use crate::io;
+
+fn drain<const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>(
+    decoder: &mut EventDecoder<BUFFER_SIZE, QUEUE_SIZE>,
+) -> Vec<io::Result<Event>> {
+    std::iter::from_fn(|| decoder.next_event()).collect()
+}
+
+#[test]
+fn it_should_decode_nothing_without_input() {
+    let mut decoder = EventDecoder::new();
+
+    assert_that!(decoder.next_event()).is_none();
+}
+
+#[test]
+fn it_should_decode_events_fed_byte_by_byte() {
+    let mut decoder = EventDecoder::new();
+
+    for byte in b"a\x1b[Ab\xff" {
+        assert_that!(decoder.feed(&[*byte])).is_equal_to(1);
+    }
+
+    assert_that!(drain(&mut decoder)).is_equal_to(vec![
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('a')))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Up))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('b')))),
+        Err(ParseError::new(b"\xff").into()),
+    ]);
+}
+
+#[test]
+fn it_should_decode_input_longer_than_buffer() {
+    let mut decoder = EventDecoder::with_config(DecoderConfig::new().with_buffer_size::<16>());
+
+    let input = "0123456789".repeat(3);
+    assert_that!(decoder.feed(input.as_bytes())).is_equal_to(input.len());
+
+    let decoded = drain(&mut decoder)
+        .into_iter()
+        .filter_map(|event| match event {
+            Ok(Event::Key(KeyEvent {
+                code: KeyCode::Char(c),
+                ..
+            })) => Some(c),
+            _ => None,
+        })
+        .collect::<String>();
+
+    assert_that!(decoded).is_equal_to(input);
+}
+
+fn truncated(bytes: &[u8]) -> Event {
+    Event::Unknown(UnknownEvent {
+        truncated: true,
+        ..UnknownEvent::new(bytes)
+    })
+}
+
+#[rstest]
+#[case::csi(
+    vec![b"\x1b[?64;1;2;6;9;15;16;17;18;21;22;28ca".as_slice()],
+    vec![truncated(b"\x1b[?64;1;2;6;9;15"), Event::Key(KeyCode::Char('a').into())],
+)]
+#[case::interrupted_csi(
+    vec![b"\x1b[?64;1;2;6;9;15;16;17\x1b[A".as_slice()],
+    vec![truncated(b"\x1b[?64;1;2;6;9;15"), Event::Key(KeyCode::Up.into())],
+)]
+#[case::osc_with_bel(
+    vec![b"\x1b]0;a title longer than sixteen bytes\x07a".as_slice()],
+    vec![truncated(b"\x1b]0;a title long"), Event::Key(KeyCode::Char('a').into())],
+)]
+#[case::osc_with_split_st(
+    vec![b"\x1b]0;a title longer than".as_slice(), b" sixteen bytes\x1b", b"\\a"],
+    vec![truncated(b"\x1b]0;a title long"), Event::Key(KeyCode::Char('a').into())],
+)]
+#[case::dcs_with_c1_st(
+    vec![b"\x90>|a version longer than sixteen bytes\x9ca".as_slice()],
+    vec![truncated(b"\x90>|a version lon"), Event::Key(KeyCode::Char('a').into())],
+)]
+fn it_should_decode_sequence_longer_than_buffer_as_truncated_unknown_event(
+    #[case] chunks: Vec<&[u8]>,
+    #[case] expected: Vec<Event>,
+) {
+    let mut decoder = EventDecoder::with_config(
+        DecoderConfig::new()
+            .with_buffer_size::<16>()
+            .with_parser_config(ParserConfig::default().with_c1_controls(true)),
+    );
+
+    let mut decoded = Vec::new();
+    for chunk in chunks {
+        assert_that!(decoder.feed(chunk)).is_equal_to(chunk.len());
+        decoded.extend(drain(&mut decoder).into_iter().map(Result::unwrap));
+    }
+
+    assert_that!(decoded).is_equal_to(expected);
+}
+
+#[test]
+fn it_should_refuse_input_while_blocked() {
+    let mut decoder = EventDecoder::with_config(
+        DecoderConfig::new()
+            .with_buffer_size::<16>()
+            .with_queue_size::<1>()
+            .with_overflow_policy(OverflowPolicy::Backpressure),
+    );
+
+    let input = b"abcdefghijklmnopqrstuvwxyz";
+    let accepted = decoder.feed(input);
+    assert_that!(accepted).is_less_than(input.len());
+
+    let mut decoded = Vec::new();
+    let mut rest = &input[accepted..];
+
+    while let Some(event) = decoder.next_event() {
+        decoded.push(event);
+        rest = &rest[decoder.feed(rest)..];
+    }
+
+    assert_that!(rest.is_empty()).is_true();
+    assert_that!(decoded.len()).is_equal_to(input.len());
+}
+
+#[test]
+fn it_should_flush_pending_escape() {
+    let mut decoder = EventDecoder::new();
+
+    decoder.feed(b"\x1b");
+    assert_that!(decoder.is_escape_pending()).is_true();
+    assert_that!(decoder.next_event()).is_none();
+
+    decoder.flush_escape();
+    assert_that!(decoder.next_event())
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Escape)));
+}
+
+#[test]
+fn it_should_not_flush_escape_of_sequence_split_across_feeds() {
+    let mut decoder = EventDecoder::new();
+
+    decoder.feed(b"\x1b[1;5");
+    assert_that!(decoder.is_escape_pending()).is_false();
+
+    decoder.flush_escape();
+    assert_that!(decoder.next_event()).is_none();
+
+    decoder.feed(b"A");
+
+    let (_, expected) = parse_bytes(b"\x1b[1;5A").unwrap();
+    assert_that!(drain(&mut decoder)).is_equal_to(vec![Ok(expected)]);
+}
+
+#[test]
+fn it_should_not_end_bracketed_paste_on_utf8_char_without_c1_controls() {
+    let mut decoder = EventDecoder::new();
+
+    for byte in "\x1b[200~aÛ201~b\x1b[201~c".as_bytes() {
+        decoder.feed(&[*byte]);
+    }
+
+    let decoded = drain(&mut decoder);
+    assert_that!(decoded.len()).is_equal_to(2);
+    assert_that!(decoded[0].as_ref().ok().and_then(Event::as_paste_event))
+        .is_some()
+        .matches(|paste| paste.as_str() == "aÛ201~b" && !paste.is_partial());
+    assert_that!(decoded[1]).is_ok_containing(Event::Key(KeyCode::Char('c').into()));
+}
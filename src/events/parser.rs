@@ -1,29 +1,127 @@
 //! Parser.
 
-use core::num::ParseIntError;
-
 use nom::branch::alt;
 use nom::bytes::complete::take_until;
-use nom::bytes::streaming::tag;
-use nom::character::streaming::{anychar, char, digit1};
+use nom::bytes::streaming::{tag, take_while_m_n};
+use nom::character::streaming::{
+    anychar, char, u8 as decimal_u8, u16 as decimal_u16, u32 as decimal_u32,
+};
 use nom::combinator::{map, map_opt, map_res, opt, success};
 use nom::error::{Error, ErrorKind};
+use nom::multi::{fold_many0, fold_many1};
 use nom::sequence::{preceded, separated_pair, terminated};
-use nom::{IResult, Parser as _};
+use nom::{Err, IResult, Needed, Parser as _};
 
 use crate::events::{
-    CursorEvent, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, ScreenEvent,
+    ColorEvent, CursorEvent, Event, KEY_TEXT_CAPACITY, KeyCode, KeyEvent, KeyEventKind,
+    KeyModifiers, KeyboardEnhancementFlags, KeypadKeyCode, MediaKeyCode, ModeReport, ModeState,
+    ModifierKeyCode, MouseButton, MouseEvent, MouseEventKind, PasteEvent, ScreenEvent,
+    TerminalResponse, UnknownEvent,
 };
+use crate::style::Color;
+
+pub use crate::io::ParseError;
 
 #[cfg(test)]
 mod tests;
 
+/// Bracketed paste end marker.
+pub(crate) const BRACKETED_PASTE_END: &str = "\x1b[201~";
+
+/// Bracketed paste end marker with an 8-bit control sequence introducer.
+pub(crate) const C1_BRACKETED_PASTE_END: &[u8] = b"\x9b201~";
+
+/// 8-bit control sequence introducer, equivalent to `ESC [`.
+pub(crate) const C1_CSI: u8 = 0x9b;
+
+/// 8-bit single shift three, equivalent to `ESC O`.
+pub(crate) const C1_SS3: u8 = 0x8f;
+
+/// 8-bit device control string, equivalent to `ESC P`.
+pub(crate) const C1_DCS: u8 = 0x90;
+
+/// 8-bit operating system command, equivalent to `ESC ]`.
+pub(crate) const C1_OSC: u8 = 0x9d;
+
+/// 8-bit string terminator, equivalent to `ESC \`.
+pub(crate) const C1_ST: u8 = 0x9c;
+
+/// Parser config.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParserConfig {
+    c1_controls: bool,
+}
+
+impl ParserConfig {
+    /// Accept 8-bit C1 introducers, e.g. `0x9b` for CSI and `0x8f` for SS3, along with their
+    /// 7-bit forms, as sent by some VT220-class terminals.
+    ///
+    /// Otherwise, these bytes are reported as invalid UTF-8.
+    pub fn with_c1_controls(mut self, enabled: bool) -> Self {
+        self.c1_controls = enabled;
+        self
+    }
+
+    pub fn c1_controls(&self) -> bool {
+        self.c1_controls
+    }
+
+    /// Tell whether the input may be parsed with this config.
+    pub(crate) fn accepts(&self, input: &[u8]) -> bool {
+        self.c1_controls
+            || !matches!(
+                input.first(),
+                Some(&(C1_CSI | C1_SS3 | C1_DCS | C1_OSC | C1_ST))
+            )
+    }
+}
+
+/// Parse a single event from text input.
+///
+/// See [`parse_bytes`] for raw input that may not be valid UTF-8.
 pub fn parse(input: &str) -> IResult<&str, Event> {
+    let suffix = |rest: &[u8]| input.get(input.len() - rest.len()..).unwrap_or_default();
+
+    match parse_bytes(input.as_bytes()) {
+        Ok((rest, event)) => Ok((suffix(rest), event)),
+        Err(err) => Err(err.map(|err| Error::new(suffix(err.input), err.code))),
+    }
+}
+
+/// Parse a single event from raw input.
+///
+/// Characters are decoded from UTF-8 on the fly, so that a partial character yields
+/// [`nom::Err::Incomplete`] until its remaining bytes are available. Bytes that can never form a
+/// valid UTF-8 character yield [`nom::Err::Failure`] whose input starts with the offending bytes,
+/// see [`invalid_utf8_len`].
+pub fn parse_bytes(input: &[u8]) -> IResult<&[u8], Event> {
+    parse_bytes_with(input, &ParserConfig::default())
+}
+
+/// Parse a single event from raw input with the given config.
+pub fn parse_bytes_with<'a>(input: &'a [u8], config: &ParserConfig) -> IResult<&'a [u8], Event> {
+    if !config.accepts(input) {
+        return Err(Err::Failure(Error::new(input, ErrorKind::Char)));
+    }
+
     alt((
+        // Must be before any CSI escape code as its markers look like function keys.
+        map(|input| parse_bracketed_paste(input, config), Event::Paste),
         parse_xterm_ss3_escape_code,
         parse_xterm_csi_escape_code,
         map(parse_xterm_ctrl_escape_code, Event::Key),
         map(parse_kitty_csi_escape_code, Event::Key),
+        map(
+            |input| parse_xterm_dcs_version_escape_code(input, config),
+            Event::Terminal,
+        ),
+        map(
+            |input| parse_xterm_osc_color_escape_code(input, config),
+            Event::Color,
+        ),
+        // Must be after any known control sequence as it consumes any well-formed one.
+        map(parse_unknown_sequence, Event::Unknown),
         // Must be last before utf-8 catch-all, otherwise it catches part of valid patterns
         // without ALT modifiers.
         map(parse_xterm_alt_escape_code, Event::Key),
@@ -33,8 +131,213 @@ pub fn parse(input: &str) -> IResult<&str, Event> {
     .parse(input)
 }
 
-pub(crate) fn parse_xterm_alt_escape_code(input: &str) -> IResult<&str, KeyEvent> {
-    map_res(preceded(char('\x1b'), parse), |event| {
+/// Get the length of the invalid UTF-8 sequence at the beginning of the input, if any.
+pub fn invalid_utf8_len(input: &[u8]) -> Option<usize> {
+    match str::from_utf8(input) {
+        Err(err) if err.valid_up_to() == 0 => err.error_len(),
+        _ => None,
+    }
+}
+
+/// Parse a complete bracketed paste, i.e. `CSI 200 ~ ... CSI 201 ~`.
+///
+/// It fails without possible recovery if the content does not fit into a single paste event. Event
+/// streams handle the markers on their own in order to split long pastes instead.
+pub(crate) fn parse_bracketed_paste<'a>(
+    input: &'a [u8],
+    config: &ParserConfig,
+) -> IResult<&'a [u8], PasteEvent> {
+    let (input, _) = parse_bracketed_paste_start(input)?;
+
+    let Some((end, marker_len)) = find_bracketed_paste_end(input, config) else {
+        return Err(Err::Incomplete(Needed::Unknown));
+    };
+
+    let content = str::from_utf8(&input[..end])
+        .ok()
+        .and_then(|content| heapless::String::try_from(content).ok())
+        .ok_or(Err::Failure(Error::new(input, ErrorKind::TooLarge)))?;
+
+    Ok((
+        &input[end + marker_len..],
+        PasteEvent {
+            content,
+            partial: false,
+        },
+    ))
+}
+
+pub(crate) fn parse_bracketed_paste_start(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    preceded(parse_csi_introducer, tag("200~")).parse(input)
+}
+
+/// Get the bracketed paste end markers accepted with the given config.
+///
+/// The 8-bit marker is only accepted along with C1 controls, as it may otherwise be part of a
+/// UTF-8 char, e.g. `Û` is `C3 9B`.
+fn bracketed_paste_end_markers(config: &ParserConfig) -> impl Iterator<Item = &'static [u8]> {
+    [
+        Some(BRACKETED_PASTE_END.as_bytes()),
+        config.c1_controls().then_some(C1_BRACKETED_PASTE_END),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Find the position and the length of the first bracketed paste end marker in the input.
+pub(crate) fn find_bracketed_paste_end(
+    input: &[u8],
+    config: &ParserConfig,
+) -> Option<(usize, usize)> {
+    bracketed_paste_end_markers(config)
+        .filter_map(|marker| {
+            input
+                .windows(marker.len())
+                .position(|window| window == marker)
+                .map(|position| (position, marker.len()))
+        })
+        .min()
+}
+
+/// Get the length of the longest suffix of the input that may be the beginning of a bracketed
+/// paste end marker.
+pub(crate) fn partial_bracketed_paste_end_len(input: &[u8], config: &ParserConfig) -> usize {
+    bracketed_paste_end_markers(config)
+        .filter_map(|marker| {
+            (1..marker.len())
+                .rev()
+                .find(|&n| input.ends_with(&marker[..n]))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Remainder of a control sequence whose beginning did not fit into the input buffer, to be
+/// skipped up to its end.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SequenceTail {
+    /// Parameter and intermediate bytes, up to the final byte.
+    Csi,
+    /// Content of an OSC or DCS string, up to the string terminator. The 8-bit terminator is only
+    /// accepted after an 8-bit introducer.
+    ControlString { c1: bool },
+}
+
+impl SequenceTail {
+    /// Get the tail of the truncated sequence that begins the input, if any.
+    pub(crate) fn of(input: &[u8], config: &ParserConfig) -> Option<Self> {
+        match input {
+            [b'\x1b', b'[', ..] => Some(SequenceTail::Csi),
+            [b'\x1b', b']' | b'P', ..] => Some(SequenceTail::ControlString { c1: false }),
+            [C1_CSI, ..] if config.c1_controls() => Some(SequenceTail::Csi),
+            [C1_OSC | C1_DCS, ..] if config.c1_controls() => {
+                Some(SequenceTail::ControlString { c1: true })
+            }
+            _ => None,
+        }
+    }
+
+    /// Get the number of bytes to skip and whether the sequence ends there.
+    ///
+    /// As for complete sequences, an unexpected byte ends the sequence right before it. A trailing
+    /// escape byte is left unskipped until the next byte tells whether it is a string terminator.
+    pub(crate) fn skip(&self, input: &[u8]) -> (usize, bool) {
+        for (i, byte) in input.iter().enumerate() {
+            match (self, byte, input.get(i + 1)) {
+                (SequenceTail::Csi, 0x20..=0x3f, _) => {}
+                (SequenceTail::Csi, 0x40..=0x7e, _) => return (i + 1, true),
+                (SequenceTail::Csi, _, _) => return (i, true),
+                (SequenceTail::ControlString { .. }, b'\x07', _) => return (i + 1, true),
+                (SequenceTail::ControlString { .. }, b'\x1b', Some(b'\\')) => return (i + 2, true),
+                (SequenceTail::ControlString { .. }, b'\x1b', None) => return (i, false),
+                (SequenceTail::ControlString { .. }, b'\x1b', Some(_)) => return (i, true),
+                (SequenceTail::ControlString { c1: true }, &C1_ST, _) => return (i + 1, true),
+                (SequenceTail::ControlString { .. }, _, _) => {}
+            }
+        }
+
+        (input.len(), false)
+    }
+}
+
+/// Parse a control sequence introducer, i.e. `ESC [` or its 8-bit form.
+pub(crate) fn parse_csi_introducer(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    alt((tag("\x1b["), tag(&[C1_CSI][..]))).parse(input)
+}
+
+/// Parse a single shift three, i.e. `ESC O` or its 8-bit form.
+pub(crate) fn parse_ss3_introducer(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    alt((tag("\x1bO"), tag(&[C1_SS3][..]))).parse(input)
+}
+
+/// Parse any well-formed control sequence, i.e. CSI, SS3, OSC or DCS, whatever its meaning.
+///
+/// A sequence interrupted by an unexpected byte ends right before it, so that the rest of the
+/// input is preserved.
+pub(crate) fn parse_unknown_sequence(input: &[u8]) -> IResult<&[u8], UnknownEvent> {
+    let (rest, _) = alt((
+        parse_csi_sequence,
+        parse_ss3_sequence,
+        parse_control_string_sequence,
+    ))
+    .parse(input)?;
+
+    Ok((rest, UnknownEvent::new(&input[..input.len() - rest.len()])))
+}
+
+/// Parse `CSI P... I... F` where parameter bytes `P` are in `0x30..=0x3f`, intermediate bytes `I`
+/// in `0x20..=0x2f` and the final byte `F` in `0x40..=0x7e`.
+fn parse_csi_sequence(input: &[u8]) -> IResult<&[u8], ()> {
+    let (mut rest, _) = parse_csi_introducer(input)?;
+
+    loop {
+        match rest.first() {
+            Some(0x20..=0x3f) => rest = &rest[1..],
+            Some(0x40..=0x7e) => return Ok((&rest[1..], ())),
+            Some(_) => return Ok((rest, ())),
+            None => return Err(Err::Incomplete(Needed::new(1))),
+        }
+    }
+}
+
+/// Parse `SS3 F` where `F` is any printable byte.
+fn parse_ss3_sequence(input: &[u8]) -> IResult<&[u8], ()> {
+    let (rest, _) = parse_ss3_introducer(input)?;
+
+    match rest.first() {
+        Some(0x20..=0x7e) => Ok((&rest[1..], ())),
+        Some(_) => Ok((rest, ())),
+        None => Err(Err::Incomplete(Needed::new(1))),
+    }
+}
+
+/// Parse `OSC ... ST` or `DCS ... ST` where the string terminator `ST` is either `ESC \\` or
+/// `BEL`.
+///
+/// The 8-bit string terminator is only accepted after an 8-bit introducer, as it may otherwise be
+/// part of a UTF-8 char.
+fn parse_control_string_sequence(input: &[u8]) -> IResult<&[u8], ()> {
+    let (mut rest, c1) = alt((
+        map(alt((tag("\x1b]"), tag("\x1bP"))), |_| false),
+        map(alt((tag(&[C1_OSC][..]), tag(&[C1_DCS][..]))), |_| true),
+    ))
+    .parse(input)?;
+
+    loop {
+        match rest {
+            [b'\x07', ..] => return Ok((&rest[1..], ())),
+            [b'\x1b', b'\\', ..] => return Ok((&rest[2..], ())),
+            [b'\x1b'] => return Err(Err::Incomplete(Needed::new(1))),
+            [b'\x1b', ..] => return Ok((rest, ())),
+            [C1_ST, ..] if c1 => return Ok((&rest[1..], ())),
+            [_, ..] => rest = &rest[1..],
+            [] => return Err(Err::Incomplete(Needed::new(1))),
+        }
+    }
+}
+
+pub(crate) fn parse_xterm_alt_escape_code(input: &[u8]) -> IResult<&[u8], KeyEvent> {
+    map_res(preceded(char('\x1b'), parse_bytes), |event| {
         if let Event::Key(key_event) = event {
             Ok(key_event.with_modifiers(KeyModifiers::ALT).sanitize())
         } else {
@@ -44,7 +347,7 @@ pub(crate) fn parse_xterm_alt_escape_code(input: &str) -> IResult<&str, KeyEvent
     .parse(input)
 }
 
-pub(crate) fn parse_xterm_ctrl_escape_code(input: &str) -> IResult<&str, KeyEvent> {
+pub(crate) fn parse_xterm_ctrl_escape_code(input: &[u8]) -> IResult<&[u8], KeyEvent> {
     alt((
         map(char('\r'), |_| KeyEvent::from(KeyCode::Enter)),
         map(char('\n'), |_| KeyEvent::from(KeyCode::Enter)),
@@ -70,28 +373,125 @@ pub(crate) fn parse_xterm_ctrl_escape_code(input: &str) -> IResult<&str, KeyEven
     .parse(input)
 }
 
-pub(crate) fn parse_utf8_char(input: &str) -> IResult<&str, KeyEvent> {
-    map(anychar, |c| {
-        if c.is_uppercase() {
-            KeyEvent::from(KeyCode::Char((c as u8 - b'A' + b'a') as char))
+pub(crate) fn parse_utf8_char(input: &[u8]) -> IResult<&[u8], KeyEvent> {
+    map(parse_utf8, |c| {
+        let mut lowercase = c.to_lowercase();
+
+        // Only chars with a single lowercase counterpart are reported as shifted.
+        match (c.is_uppercase(), lowercase.next(), lowercase.next()) {
+            (true, Some(lowercase), None) => KeyEvent::from(KeyCode::Char(lowercase))
                 .with_modifiers(KeyModifiers::SHIFT)
-                .sanitize()
-        } else {
-            KeyEvent::from(KeyCode::Char(c))
+                .sanitize(),
+            _ => KeyEvent::from(KeyCode::Char(c)),
         }
     })
     .parse(input)
 }
 
-pub(crate) fn parse_utf8_codepoint(input: &str) -> IResult<&str, KeyCode> {
-    map_opt(map_res(digit1, str::parse::<u32>), char::from_u32)
-        .map(KeyCode::Char)
-        .parse(input)
+/// Decode a single UTF-8 encoded char.
+pub(crate) fn parse_utf8(input: &[u8]) -> IResult<&[u8], char> {
+    let Some(&first) = input.first() else {
+        return Err(Err::Incomplete(Needed::new(1)));
+    };
+
+    let width = match first {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return Err(Err::Failure(Error::new(input, ErrorKind::Char))),
+    };
+
+    let Some(bytes) = input.get(..width) else {
+        // Wait for the remaining bytes, unless the available ones are already invalid.
+        return match invalid_utf8_len(input) {
+            Some(_) => Err(Err::Failure(Error::new(input, ErrorKind::Char))),
+            None => Err(Err::Incomplete(Needed::new(width - input.len()))),
+        };
+    };
+
+    match str::from_utf8(bytes).ok().and_then(|s| s.chars().next()) {
+        Some(c) => Ok((&input[width..], c)),
+        None => Err(Err::Failure(Error::new(input, ErrorKind::Char))),
+    }
+}
+
+pub(crate) fn parse_utf8_codepoint(input: &[u8]) -> IResult<&[u8], KeyCode> {
+    map_opt(decimal_u32, interpret_kitty_codepoint_value).parse(input)
+}
+
+/// Interpret a kitty keyboard protocol codepoint, either a functional key or a character.
+pub(crate) fn interpret_kitty_codepoint_value(value: u32) -> Option<KeyCode> {
+    let keycode = match value {
+        9 => KeyCode::Tab,
+        13 => KeyCode::Enter,
+        27 => KeyCode::Escape,
+        127 => KeyCode::Backspace,
+        57358 => KeyCode::CapsLock,
+        57359 => KeyCode::ScrollLock,
+        57360 => KeyCode::NumLock,
+        57361 => KeyCode::PrintScreen,
+        57362 => KeyCode::Pause,
+        57363 => KeyCode::Menu,
+        n @ 57376..=57398 => KeyCode::Fn((n - 57376 + 13) as u8),
+        n @ 57399..=57408 => KeyCode::Keypad(KeypadKeyCode::Digit((n - 57399) as u8)),
+        57409 => KeyCode::Keypad(KeypadKeyCode::Decimal),
+        57410 => KeyCode::Keypad(KeypadKeyCode::Divide),
+        57411 => KeyCode::Keypad(KeypadKeyCode::Multiply),
+        57412 => KeyCode::Keypad(KeypadKeyCode::Subtract),
+        57413 => KeyCode::Keypad(KeypadKeyCode::Add),
+        57414 => KeyCode::Keypad(KeypadKeyCode::Enter),
+        57415 => KeyCode::Keypad(KeypadKeyCode::Equal),
+        57416 => KeyCode::Keypad(KeypadKeyCode::Separator),
+        57417 => KeyCode::Keypad(KeypadKeyCode::Left),
+        57418 => KeyCode::Keypad(KeypadKeyCode::Right),
+        57419 => KeyCode::Keypad(KeypadKeyCode::Up),
+        57420 => KeyCode::Keypad(KeypadKeyCode::Down),
+        57421 => KeyCode::Keypad(KeypadKeyCode::PageUp),
+        57422 => KeyCode::Keypad(KeypadKeyCode::PageDown),
+        57423 => KeyCode::Keypad(KeypadKeyCode::Home),
+        57424 => KeyCode::Keypad(KeypadKeyCode::End),
+        57425 => KeyCode::Keypad(KeypadKeyCode::Insert),
+        57426 => KeyCode::Keypad(KeypadKeyCode::Delete),
+        57427 => KeyCode::Keypad(KeypadKeyCode::Begin),
+        57428 => KeyCode::Media(MediaKeyCode::Play),
+        57429 => KeyCode::Media(MediaKeyCode::Pause),
+        57430 => KeyCode::Media(MediaKeyCode::PlayPause),
+        57431 => KeyCode::Media(MediaKeyCode::Reverse),
+        57432 => KeyCode::Media(MediaKeyCode::Stop),
+        57433 => KeyCode::Media(MediaKeyCode::FastForward),
+        57434 => KeyCode::Media(MediaKeyCode::Rewind),
+        57435 => KeyCode::Media(MediaKeyCode::TrackNext),
+        57436 => KeyCode::Media(MediaKeyCode::TrackPrevious),
+        57437 => KeyCode::Media(MediaKeyCode::Record),
+        57438 => KeyCode::Media(MediaKeyCode::LowerVolume),
+        57439 => KeyCode::Media(MediaKeyCode::RaiseVolume),
+        57440 => KeyCode::Media(MediaKeyCode::MuteVolume),
+        57441 => KeyCode::Modifier(ModifierKeyCode::LeftShift),
+        57442 => KeyCode::Modifier(ModifierKeyCode::LeftControl),
+        57443 => KeyCode::Modifier(ModifierKeyCode::LeftAlt),
+        57444 => KeyCode::Modifier(ModifierKeyCode::LeftSuper),
+        57445 => KeyCode::Modifier(ModifierKeyCode::LeftHyper),
+        57446 => KeyCode::Modifier(ModifierKeyCode::LeftMeta),
+        57447 => KeyCode::Modifier(ModifierKeyCode::RightShift),
+        57448 => KeyCode::Modifier(ModifierKeyCode::RightControl),
+        57449 => KeyCode::Modifier(ModifierKeyCode::RightAlt),
+        57450 => KeyCode::Modifier(ModifierKeyCode::RightSuper),
+        57451 => KeyCode::Modifier(ModifierKeyCode::RightHyper),
+        57452 => KeyCode::Modifier(ModifierKeyCode::RightMeta),
+        57453 => KeyCode::Modifier(ModifierKeyCode::IsoLevel3Shift),
+        57454 => KeyCode::Modifier(ModifierKeyCode::IsoLevel5Shift),
+        // Remaining private use area codepoints are reserved for future functional keys.
+        57344..=63743 => return None,
+        _ => KeyCode::Char(char::from_u32(value)?),
+    };
+
+    Some(keycode)
 }
 
-pub(crate) fn parse_xterm_ss3_escape_code(input: &str) -> IResult<&str, Event> {
+pub(crate) fn parse_xterm_ss3_escape_code(input: &[u8]) -> IResult<&[u8], Event> {
     preceded(
-        tag("\x1bO"),
+        parse_ss3_introducer,
         alt((
             map(char('A'), |_| Event::Key(KeyCode::Up.into())),
             map(char('B'), |_| Event::Key(KeyCode::Down.into())),
@@ -108,9 +508,9 @@ pub(crate) fn parse_xterm_ss3_escape_code(input: &str) -> IResult<&str, Event> {
     .parse(input)
 }
 
-pub(crate) fn parse_xterm_csi_escape_code(input: &str) -> IResult<&str, Event> {
+pub(crate) fn parse_xterm_csi_escape_code(input: &[u8]) -> IResult<&[u8], Event> {
     preceded(
-        tag("\x1b["),
+        parse_csi_introducer,
         alt((
             map(char('\x1b'), |_| Event::Key(KeyCode::Escape.into())),
             map(char('A'), |_| Event::Key(KeyCode::Up.into())),
@@ -119,6 +519,7 @@ pub(crate) fn parse_xterm_csi_escape_code(input: &str) -> IResult<&str, Event> {
             map(char('D'), |_| Event::Key(KeyCode::Left.into())),
             map(char('H'), |_| Event::Key(KeyCode::Home.into())),
             map(char('F'), |_| Event::Key(KeyCode::End.into())),
+            map(char('E'), |_| Event::Key(KeypadKeyCode::Begin.into())),
             map(char('Z'), |_| {
                 Event::Key(
                     KeyEvent::from(KeyCode::BackTab)
@@ -132,7 +533,18 @@ pub(crate) fn parse_xterm_csi_escape_code(input: &str) -> IResult<&str, Event> {
                 preceded(char(';'), parse_csi_modifier_encoded_escape_code),
                 Event::Key,
             ),
+            map(parse_xterm_csi_window_size_escape_code, Event::Screen),
+            map(
+                parse_xterm_csi_device_attributes_escape_code,
+                Event::Terminal,
+            ),
+            map(parse_xterm_csi_mode_report_escape_code, Event::Mode),
             map(parse_xterm_csi_function_key, Event::Key),
+            map(
+                parse_kitty_csi_keyboard_enhancement_flags,
+                Event::KeyboardEnhancementFlags,
+            ),
+            map(parse_xterm_csi_mouse_escape_code, Event::Mouse),
             map(parse_xterm_csi_cursor_escape_code, Event::Cursor),
             map(parse_xterm_vt220_csi_escape_code, Event::Key),
             // map(parse_csi_modifier_encoded_escape_code, Event::Key),
@@ -141,12 +553,12 @@ pub(crate) fn parse_xterm_csi_escape_code(input: &str) -> IResult<&str, Event> {
     .parse(input)
 }
 
-pub(crate) fn parse_xterm_csi_function_key(input: &str) -> IResult<&str, KeyEvent> {
+pub(crate) fn parse_xterm_csi_function_key(input: &[u8]) -> IResult<&[u8], KeyEvent> {
     map_res(
         terminated(
             (
                 alt((char('1'), char('2'))),
-                map_res(digit1, str::parse::<u8>),
+                decimal_u8,
                 opt(preceded(char(';'), parse_xterm_csi_key_modifiers)),
             ),
             char('~'),
@@ -168,27 +580,171 @@ pub(crate) fn parse_xterm_csi_function_key(input: &str) -> IResult<&str, KeyEven
     .parse(input)
 }
 
-pub(crate) fn parse_xterm_csi_cursor_escape_code(input: &str) -> IResult<&str, CursorEvent> {
-    terminated(
-        separated_pair(
-            map_res(digit1, |s: &str| s.parse::<u16>()),
-            char(';'),
-            map_res(digit1, |s: &str| s.parse::<u16>()),
+pub(crate) fn parse_xterm_csi_mouse_escape_code(input: &[u8]) -> IResult<&[u8], MouseEvent> {
+    alt((
+        parse_sgr_mouse_escape_code,
+        parse_x10_mouse_escape_code,
+        parse_urxvt_mouse_escape_code,
+    ))
+    .parse(input)
+}
+
+/// Parse SGR (1006) encoded mouse event, i.e. `CSI < Cb ; Cx ; Cy (M | m)`.
+pub(crate) fn parse_sgr_mouse_escape_code(input: &[u8]) -> IResult<&[u8], MouseEvent> {
+    map_opt(
+        preceded(
+            char('<'),
+            (
+                decimal_u16,
+                preceded(char(';'), decimal_u16),
+                preceded(char(';'), decimal_u16),
+                alt((char('M'), char('m'))),
+            ),
+        ),
+        |(cb, cx, cy, last)| interpret_mouse_event(cb, cx, cy, last == 'm'),
+    )
+    .parse(input)
+}
+
+/// Parse legacy X10 (1000) encoded mouse event, i.e. `CSI M Cb Cx Cy` where each value is offset
+/// by 32.
+pub(crate) fn parse_x10_mouse_escape_code(input: &[u8]) -> IResult<&[u8], MouseEvent> {
+    map_opt(
+        preceded(char('M'), (anychar, anychar, anychar)),
+        |(cb, cx, cy)| {
+            let cb = (cb as u32).checked_sub(32)?;
+            let cx = (cx as u32).checked_sub(32)?;
+            let cy = (cy as u32).checked_sub(32)?;
+            interpret_mouse_event(
+                u16::try_from(cb).ok()?,
+                u16::try_from(cx).ok()?,
+                u16::try_from(cy).ok()?,
+                false,
+            )
+        },
+    )
+    .parse(input)
+}
+
+/// Parse urxvt (1015) encoded mouse event, i.e. `CSI Cb ; Cx ; Cy M` where `Cb` is offset by 32.
+pub(crate) fn parse_urxvt_mouse_escape_code(input: &[u8]) -> IResult<&[u8], MouseEvent> {
+    map_opt(
+        terminated(
+            (
+                decimal_u16,
+                preceded(char(';'), decimal_u16),
+                preceded(char(';'), decimal_u16),
+            ),
+            char('M'),
         ),
+        |(cb, cx, cy)| interpret_mouse_event(cb.checked_sub(32)?, cx, cy, false),
+    )
+    .parse(input)
+}
+
+/// Interpret mouse button mask and one-based coordinates into a mouse event.
+///
+/// Legacy encodings do not tell which button has been released, so that it defaults to the left
+/// one as most terminals do.
+pub(crate) fn interpret_mouse_event(
+    cb: u16,
+    cx: u16,
+    cy: u16,
+    released: bool,
+) -> Option<MouseEvent> {
+    let button = match cb & 0b11 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        _ => MouseButton::Right,
+    };
+
+    let kind = match (cb & 0b1100_0000, cb & 0b10_0000 != 0, cb & 0b11) {
+        (0b0100_0000, _, 0) => MouseEventKind::ScrollUp,
+        (0b0100_0000, _, 1) => MouseEventKind::ScrollDown,
+        (0b0100_0000, _, 2) => MouseEventKind::ScrollLeft,
+        (0b0100_0000, _, _) => MouseEventKind::ScrollRight,
+        (0, true, 3) => MouseEventKind::Moved,
+        (0, true, _) => MouseEventKind::Drag(button),
+        (0, false, 3) => MouseEventKind::Up(MouseButton::Left),
+        (0, false, _) if released => MouseEventKind::Up(button),
+        (0, false, _) => MouseEventKind::Down(button),
+        _ => return None,
+    };
+
+    let mut modifiers = KeyModifiers::empty();
+
+    if cb & 4 != 0 {
+        modifiers |= KeyModifiers::SHIFT;
+    }
+
+    if cb & 8 != 0 {
+        modifiers |= KeyModifiers::ALT;
+    }
+
+    if cb & 16 != 0 {
+        modifiers |= KeyModifiers::CONTROL;
+    }
+
+    Some(
+        MouseEvent::new(kind, cx.saturating_sub(1), cy.saturating_sub(1)).with_modifiers(modifiers),
+    )
+}
+
+pub(crate) fn parse_xterm_csi_cursor_escape_code(input: &[u8]) -> IResult<&[u8], CursorEvent> {
+    terminated(
+        separated_pair(decimal_u16, char(';'), decimal_u16),
         char('R'),
     )
     .map(|(y, x)| CursorEvent::Positioned { x, y })
     .parse(input)
 }
 
-pub(crate) fn parse_xterm_vt220_csi_escape_code(input: &str) -> IResult<&str, KeyEvent> {
+/// Parse either the reply to [`crate::terminal::QuerySize`], i.e. `CSI 8 ; height ; width t`, or
+/// an in-band resize notification, i.e. `CSI 48 ; height ; width ; pixel height ; pixel width t`.
+pub(crate) fn parse_xterm_csi_window_size_escape_code(input: &[u8]) -> IResult<&[u8], ScreenEvent> {
+    alt((
+        preceded(
+            tag("8;"),
+            terminated(
+                separated_pair(decimal_u16, char(';'), decimal_u16),
+                char('t'),
+            ),
+        )
+        .map(|(height, width)| ScreenEvent::Resized {
+            width,
+            height,
+            pixel_width: 0,
+            pixel_height: 0,
+        }),
+        preceded(
+            tag("48;"),
+            terminated(
+                (
+                    terminated(decimal_u16, char(';')),
+                    terminated(decimal_u16, char(';')),
+                    terminated(decimal_u16, char(';')),
+                    decimal_u16,
+                ),
+                char('t'),
+            ),
+        )
+        .map(
+            |(height, width, pixel_height, pixel_width)| ScreenEvent::Resized {
+                width,
+                height,
+                pixel_width,
+                pixel_height,
+            },
+        ),
+    ))
+    .parse(input)
+}
+
+pub(crate) fn parse_xterm_vt220_csi_escape_code(input: &[u8]) -> IResult<&[u8], KeyEvent> {
     terminated(
         map(
             (
-                map_opt(
-                    map_res(digit1, |s: &str| s.parse::<u8>()),
-                    interpret_xterm_vt220_csi_code_value,
-                ),
+                map_opt(decimal_u16, interpret_xterm_vt220_csi_code_value),
                 opt(preceded(char(';'), parse_xterm_csi_key_modifiers)),
             ),
             KeyEvent::from,
@@ -198,7 +754,7 @@ pub(crate) fn parse_xterm_vt220_csi_escape_code(input: &str) -> IResult<&str, Ke
     .parse(input)
 }
 
-pub(crate) fn interpret_xterm_vt220_csi_code_value(value: u8) -> Option<KeyCode> {
+pub(crate) fn interpret_xterm_vt220_csi_code_value(value: u16) -> Option<KeyCode> {
     let keycode = match value {
         1 | 7 => KeyCode::Home,
         2 => KeyCode::Insert,
@@ -206,21 +762,22 @@ pub(crate) fn interpret_xterm_vt220_csi_code_value(value: u8) -> Option<KeyCode>
         4 | 8 => KeyCode::End,
         5 => KeyCode::PageUp,
         6 => KeyCode::PageDown,
-        n @ 11..=15 => KeyCode::Fn(n - 10),
-        n @ 17..=21 => KeyCode::Fn(n - 11),
-        n @ 23..=26 => KeyCode::Fn(n - 12),
-        n @ 28..=29 => KeyCode::Fn(n - 15),
-        n @ 31..=34 => KeyCode::Fn(n - 17),
+        n @ 11..=15 => KeyCode::Fn((n - 10) as u8),
+        n @ 17..=21 => KeyCode::Fn((n - 11) as u8),
+        n @ 23..=26 => KeyCode::Fn((n - 12) as u8),
+        n @ 28..=29 => KeyCode::Fn((n - 15) as u8),
+        n @ 31..=34 => KeyCode::Fn((n - 17) as u8),
+        57427 => KeyCode::Keypad(KeypadKeyCode::Begin),
         _ => return None,
     };
 
     Some(keycode)
 }
 
-pub(crate) fn parse_kitty_csi_escape_code(input: &str) -> IResult<&str, KeyEvent> {
+pub(crate) fn parse_kitty_csi_escape_code(input: &[u8]) -> IResult<&[u8], KeyEvent> {
     alt((
         preceded(
-            tag("\x1b["),
+            parse_csi_introducer,
             terminated(
                 alt((
                     map(
@@ -237,10 +794,20 @@ pub(crate) fn parse_kitty_csi_escape_code(input: &str) -> IResult<&str, KeyEvent
                                     success((KeyModifiers::empty(), KeyEventKind::Pressed)),
                                 )),
                             )),
-                            opt(preceded(char(';'), digit1)),
+                            opt(preceded(char(';'), parse_kitty_csi_associated_text)),
                         ),
-                        |(key_code, key_modifiers_and_kind, _)| {
-                            KeyEvent::from((key_code, key_modifiers_and_kind))
+                        |(
+                            (key_code, shifted_key, base_layout_key),
+                            key_modifiers_and_kind,
+                            text,
+                        )| {
+                            let key_event = KeyEvent::from((key_code, key_modifiers_and_kind))
+                                .with_alternate_keys(shifted_key, base_layout_key);
+
+                            match text {
+                                Some(text) => key_event.with_text(&text),
+                                None => key_event,
+                            }
                         },
                     ),
                 )),
@@ -256,39 +823,264 @@ pub(crate) fn parse_kitty_csi_escape_code(input: &str) -> IResult<&str, KeyEvent
     .parse(input)
 }
 
-pub(crate) fn parse_kitty_csi_codepoint(input: &str) -> IResult<&str, KeyCode> {
+/// Parse the reply to either a primary or a secondary device attributes request, i.e.
+/// `CSI ? class ; attributes c` or `CSI > type ; version ; cartridge c`.
+pub(crate) fn parse_xterm_csi_device_attributes_escape_code(
+    input: &[u8],
+) -> IResult<&[u8], TerminalResponse> {
+    alt((
+        preceded(
+            char('?'),
+            terminated(
+                (
+                    decimal_u16,
+                    fold_many0(
+                        preceded(char(';'), opt(decimal_u16)),
+                        heapless::Vec::new,
+                        |mut attributes, attribute| {
+                            // Empty and extra attributes are dropped.
+                            if let Some(attribute) = attribute {
+                                let _ = attributes.push(attribute);
+                            }
+                            attributes
+                        },
+                    ),
+                ),
+                char('c'),
+            ),
+        )
+        .map(|(class, attributes)| TerminalResponse::PrimaryDeviceAttributes { class, attributes }),
+        preceded(
+            char('>'),
+            terminated(
+                (
+                    decimal_u16,
+                    preceded(char(';'), decimal_u16),
+                    opt(preceded(char(';'), decimal_u16)),
+                ),
+                char('c'),
+            ),
+        )
+        .map(|(terminal_type, version, cartridge)| {
+            TerminalResponse::SecondaryDeviceAttributes {
+                terminal_type,
+                version,
+                cartridge: cartridge.unwrap_or(0),
+            }
+        }),
+    ))
+    .parse(input)
+}
+
+/// Parse the reply to a mode request, i.e. `CSI ? mode ; state $ y` or `CSI mode ; state $ y`.
+pub(crate) fn parse_xterm_csi_mode_report_escape_code(input: &[u8]) -> IResult<&[u8], ModeReport> {
+    map_opt(
+        terminated(
+            (
+                map(opt(char('?')), |private| private.is_some()),
+                separated_pair(decimal_u16, char(';'), decimal_u8),
+            ),
+            tag("$y"),
+        ),
+        |(private, (mode, state))| {
+            let state = match state {
+                0 => ModeState::NotRecognized,
+                1 => ModeState::Set,
+                2 => ModeState::Reset,
+                3 => ModeState::PermanentlySet,
+                4 => ModeState::PermanentlyReset,
+                _ => return None,
+            };
+
+            Some(ModeReport {
+                mode,
+                private,
+                state,
+            })
+        },
+    )
+    .parse(input)
+}
+
+/// Parse the reply to a terminal version request, i.e. `DCS > | text ST`.
+///
+/// The text is truncated on a char boundary if it does not fit into the response.
+pub(crate) fn parse_xterm_dcs_version_escape_code<'a>(
+    input: &'a [u8],
+    config: &ParserConfig,
+) -> IResult<&'a [u8], TerminalResponse> {
+    let (rest, c1) = alt((
+        map(tag("\x1bP>|"), |_| false),
+        map(tag(&[C1_DCS, b'>', b'|'][..]), |_| true),
+    ))
+    .parse(input)?;
+
+    if c1 && !config.c1_controls() {
+        return Err(Err::Error(Error::new(input, ErrorKind::Tag)));
+    }
+
+    let mut end = 0;
+    let terminator_len = loop {
+        match &rest[end..] {
+            [b'\x07', ..] => break 1,
+            [b'\x1b', b'\\', ..] => break 2,
+            [C1_ST, ..] if c1 => break 1,
+            [b'\x1b'] | [] => return Err(Err::Incomplete(Needed::new(1))),
+            // Let an interrupted string be reported as an unknown sequence.
+            [b'\x1b', ..] => return Err(Err::Error(Error::new(input, ErrorKind::Tag))),
+            [_, ..] => end += 1,
+        }
+    };
+
+    let text =
+        str::from_utf8(&rest[..end]).map_err(|_| Err::Error(Error::new(input, ErrorKind::Char)))?;
+
+    let mut version = heapless::String::new();
+    for c in text.chars() {
+        if version.push(c).is_err() {
+            break;
+        }
+    }
+
+    Ok((
+        &rest[end + terminator_len..],
+        TerminalResponse::Version(version),
+    ))
+}
+
+/// Parse the reply to a color query, i.e. `OSC 10 ; rgb:r/g/b ST`, `OSC 11 ; rgb:r/g/b ST` or
+/// `OSC 4 ; index ; rgb:r/g/b ST` for the foreground, background or palette color respectively.
+pub(crate) fn parse_xterm_osc_color_escape_code<'a>(
+    input: &'a [u8],
+    config: &ParserConfig,
+) -> IResult<&'a [u8], ColorEvent> {
+    let (rest, c1) = alt((
+        map(tag("\x1b]"), |_| false),
+        map(tag(&[C1_OSC][..]), |_| true),
+    ))
+    .parse(input)?;
+
+    if c1 && !config.c1_controls() {
+        return Err(Err::Error(Error::new(input, ErrorKind::Tag)));
+    }
+
+    let (rest, event) = alt((
+        preceded(tag("10;"), parse_xterm_rgb_color).map(ColorEvent::Foreground),
+        preceded(tag("11;"), parse_xterm_rgb_color).map(ColorEvent::Background),
+        preceded(
+            tag("4;"),
+            separated_pair(decimal_u8, char(';'), parse_xterm_rgb_color),
+        )
+        .map(|(index, color)| ColorEvent::Palette { index, color }),
+    ))
+    .parse(rest)?;
+
+    match rest {
+        [b'\x07', ..] => Ok((&rest[1..], event)),
+        [b'\x1b', b'\\', ..] => Ok((&rest[2..], event)),
+        [C1_ST, ..] if c1 => Ok((&rest[1..], event)),
+        [] | [b'\x1b'] => Err(Err::Incomplete(Needed::new(1))),
+        _ => Err(Err::Error(Error::new(input, ErrorKind::Tag))),
+    }
+}
+
+/// Parse a color specification, i.e. `rgb:r/g/b` where each component is made of 1 to 4
+/// hexadecimal digits, scaled down to 8 bits.
+pub(crate) fn parse_xterm_rgb_color(input: &[u8]) -> IResult<&[u8], Color> {
+    let component = || {
+        map_res(
+            take_while_m_n(1, 4, |byte: u8| byte.is_ascii_hexdigit()),
+            |digits: &[u8]| {
+                let digits = str::from_utf8(digits).map_err(|_| ErrorKind::HexDigit)?;
+                let value = u32::from_str_radix(digits, 16).map_err(|_| ErrorKind::HexDigit)?;
+                let max = (1 << (4 * digits.len())) - 1;
+                Ok::<_, ErrorKind>((value * 255 / max) as u8)
+            },
+        )
+    };
+
+    preceded(
+        tag("rgb:"),
+        (
+            component(),
+            preceded(char('/'), component()),
+            preceded(char('/'), component()),
+        ),
+    )
+    .map(|(r, g, b)| Color::Rgb { r, g, b })
+    .parse(input)
+}
+
+/// Parse the reply to a keyboard enhancement flags query, i.e. `CSI ? flags u`.
+pub(crate) fn parse_kitty_csi_keyboard_enhancement_flags(
+    input: &[u8],
+) -> IResult<&[u8], KeyboardEnhancementFlags> {
     map(
-        (parse_utf8_codepoint, opt(preceded(char(':'), digit1))),
-        |(key_code, _)| key_code,
+        preceded(char('?'), terminated(decimal_u8, char('u'))),
+        KeyboardEnhancementFlags::from_bits_truncate,
     )
     .parse(input)
 }
 
-pub(crate) fn parse_xterm_csi_key_modifiers(input: &str) -> IResult<&str, KeyModifiers> {
-    map_res(digit1, |s: &str| {
-        s.parse::<u8>().map(interpret_xterm_key_modifiers_from_mask)
-    })
+/// Key code along with its optional shifted and base layout alternate keys.
+pub(crate) type KittyKeyCodes = (KeyCode, Option<KeyCode>, Option<KeyCode>);
+
+/// Parse a key codepoint with its optional alternate keys, i.e. `code[:shifted[:base]]`.
+pub(crate) fn parse_kitty_csi_codepoint(input: &[u8]) -> IResult<&[u8], KittyKeyCodes> {
+    map(
+        (
+            parse_utf8_codepoint,
+            opt(preceded(
+                char(':'),
+                (
+                    opt(parse_utf8_codepoint),
+                    opt(preceded(char(':'), parse_utf8_codepoint)),
+                ),
+            )),
+        ),
+        |(key_code, alternate_keys)| {
+            let (shifted_key, base_layout_key) = alternate_keys.unwrap_or_default();
+            (key_code, shifted_key, base_layout_key)
+        },
+    )
     .parse(input)
 }
 
-pub(crate) fn interpret_xterm_key_modifiers_from_mask(mask: u8) -> KeyModifiers {
-    interpret_kitty_key_modifiers_from_mask(mask.saturating_sub(1))
+/// Parse the text associated to a key, i.e. colon separated codepoints.
+///
+/// The text is truncated to [`KEY_TEXT_CAPACITY`] bytes.
+pub(crate) fn parse_kitty_csi_associated_text(
+    input: &[u8],
+) -> IResult<&[u8], heapless::String<KEY_TEXT_CAPACITY>> {
+    fold_many1(
+        preceded(opt(char(':')), map_opt(decimal_u32, char::from_u32)),
+        heapless::String::new,
+        |mut text, c| {
+            let _ = text.push(c);
+            text
+        },
+    )
+    .parse(input)
+}
+
+pub(crate) fn parse_xterm_csi_key_modifiers(input: &[u8]) -> IResult<&[u8], KeyModifiers> {
+    map(decimal_u16, interpret_xterm_key_modifiers_from_mask).parse(input)
+}
+
+/// Interpret modifiers encoded as `1 + mask`, as both xterm and kitty keyboard protocol do.
+pub(crate) fn interpret_xterm_key_modifiers_from_mask(mask: u16) -> KeyModifiers {
+    interpret_kitty_key_modifiers_from_mask(mask.saturating_sub(1) as u8)
 }
 
 pub(crate) fn parse_kitty_csi_key_modifiers_and_kind(
-    input: &str,
-) -> IResult<&str, (KeyModifiers, KeyEventKind)> {
+    input: &[u8],
+) -> IResult<&[u8], (KeyModifiers, KeyEventKind)> {
     (
-        map_res(digit1, |s: &str| {
-            s.parse::<u8>().map(interpret_kitty_key_modifiers_from_mask)
-        }),
+        map(decimal_u16, interpret_xterm_key_modifiers_from_mask),
         alt((
             preceded(
                 char(':'),
-                map_res(digit1, |s: &str| {
-                    s.parse::<u8>()
-                        .map(interpret_kitty_key_event_kind_from_value)
-                }),
+                map(decimal_u8, interpret_kitty_key_event_kind_from_value),
             ),
             success(KeyEventKind::Pressed),
         )),
@@ -315,10 +1107,22 @@ pub(crate) fn interpret_kitty_key_modifiers_from_mask(mask: u8) -> KeyModifiers
         modifiers |= KeyModifiers::SUPER;
     }
 
+    if mask & 16 != 0 {
+        modifiers |= KeyModifiers::HYPER;
+    }
+
     if mask & 32 != 0 {
         modifiers |= KeyModifiers::META;
     }
 
+    if mask & 64 != 0 {
+        modifiers |= KeyModifiers::CAPS_LOCK;
+    }
+
+    if mask & 128 != 0 {
+        modifiers |= KeyModifiers::NUM_LOCK;
+    }
+
     modifiers
 }
 
@@ -331,7 +1135,7 @@ pub(crate) fn interpret_kitty_key_event_kind_from_value(value: u8) -> KeyEventKi
     }
 }
 
-pub(crate) fn parse_csi_modifier_encoded_escape_code(input: &str) -> IResult<&str, KeyEvent> {
+pub(crate) fn parse_csi_modifier_encoded_escape_code(input: &[u8]) -> IResult<&[u8], KeyEvent> {
     (
         preceded(
             take_until(";"),
@@ -339,12 +1143,11 @@ pub(crate) fn parse_csi_modifier_encoded_escape_code(input: &str) -> IResult<&st
                 char(';'),
                 alt((
                     parse_kitty_csi_key_modifiers_and_kind,
-                    map_res(digit1, |s: &str| {
-                        Ok::<_, ParseIntError>((
-                            s.parse::<u8>()
-                                .map(interpret_xterm_key_modifiers_from_mask)?,
+                    map(decimal_u16, |mask| {
+                        (
+                            interpret_xterm_key_modifiers_from_mask(mask),
                             KeyEventKind::Pressed,
-                        ))
+                        )
                     }),
                     success((KeyModifiers::empty(), KeyEventKind::Pressed)),
                 )),
@@ -370,6 +1173,7 @@ pub(crate) fn interpret_modifier_key_code_value(value: char) -> Option<KeyCode>
         'B' => KeyCode::Down,
         'C' => KeyCode::Right,
         'D' => KeyCode::Left,
+        'E' => KeyCode::Keypad(KeypadKeyCode::Begin),
         'F' => KeyCode::End,
         'H' => KeyCode::Home,
         'P' => KeyCode::Fn(1),
@@ -3,7 +3,13 @@
 use rstest::rstest;
 use speculoos::prelude::*;
 
-use crate::events::{Event, KeyCode, KeyEvent, KeyModifiers, parse};
+use crate::events::{
+    ColorEvent, Event, KeyCode, KeyEvent, KeyModifiers, KeyboardEnhancementFlags, KeypadKeyCode,
+    MediaKeyCode, ModeReport, ModeState, ModifierKeyCode, MouseButton, MouseEvent, MouseEventKind,
+    ParserConfig, ScreenEvent, TerminalResponse, UnknownEvent, parse, parse_bytes,
+    parse_bytes_with, parser,
+};
+use crate::style::Color;
 
 #[rstest]
 // SS3 Arrow keys.
@@ -38,6 +44,11 @@ use crate::events::{Event, KeyCode, KeyEvent, KeyModifiers, parse};
 #[case::xterm_csi_function_keys("\x1b[21~", Event::Key(KeyCode::Fn(10).into()))]
 #[case::xterm_csi_function_keys("\x1b[23~", Event::Key(KeyCode::Fn(11).into()))]
 #[case::xterm_csi_function_keys("\x1b[24~", Event::Key(KeyCode::Fn(12).into()))]
+// CSI keys with xterm modifiers.
+#[case::xterm_csi_keys_with_modifiers(
+    "\x1b[2;129~",
+    Event::Key(KeyEvent::from(KeyCode::Insert).with_modifiers(KeyModifiers::NUM_LOCK)),
+)]
 // CSI Function keys with modifiers.
 #[case::xterm_csi_function_keys_with_modifiers(
     "\x1b[15;2~]",
@@ -59,27 +70,109 @@ use crate::events::{Event, KeyCode, KeyEvent, KeyModifiers, parse};
 #[case::kitty_csi_unambiguous_key("\x1b[97u", Event::Key(KeyEvent::from(KeyCode::Char('a'))))]
 // Kitty keyboard protocol with modifiers.
 #[case::kitty_csi_unambiguous_key(
-    "\x1b[97;1u",
+    "\x1b[97;2u",
     Event::Key(KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::SHIFT)),
 )]
 #[case::kitty_csi_unambiguous_key(
-    "\x1b[97;2u",
+    "\x1b[97;3u",
     Event::Key(KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::ALT)),
 )]
 #[case::kitty_csi_unambiguous_key(
-    "\x1b[97;4u",
+    "\x1b[97;5u",
     Event::Key(KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::CONTROL)),
 )]
 #[case::kitty_csi_unambiguous_key(
-    "\x1b[97;8u",
+    "\x1b[97;9u",
     Event::Key(KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::SUPER)),
 )]
 #[case::kitty_csi_unambiguous_key(
-    "\x1b[97;1;65u",
-    Event::Key(KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::SHIFT)),
+    "\x1b[97;2;65u",
+    Event::Key(
+        KeyEvent::from(KeyCode::Char('a'))
+            .with_modifiers(KeyModifiers::SHIFT)
+            .with_text("A")
+    ),
+)]
+// Kitty keyboard protocol with lock states.
+#[case::kitty_csi_lock_states(
+    "\x1b[97;65u",
+    Event::Key(KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::CAPS_LOCK)),
+)]
+#[case::kitty_csi_lock_states(
+    "\x1b[57400;129u",
+    Event::Key(KeyEvent::from(KeypadKeyCode::Digit(1)).with_modifiers(KeyModifiers::NUM_LOCK)),
+)]
+#[case::kitty_csi_lock_states(
+    "\x1b[97;18u",
+    Event::Key(
+        KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::SHIFT | KeyModifiers::HYPER)
+    ),
+)]
+// Kitty keyboard protocol with alternate keys.
+#[case::kitty_csi_alternate_keys(
+    "\x1b[97:65:113u",
+    Event::Key(
+        KeyEvent::from(KeyCode::Char('a'))
+            .with_alternate_keys(Some(KeyCode::Char('A')), Some(KeyCode::Char('q')))
+    ),
+)]
+#[case::kitty_csi_alternate_keys(
+    "\x1b[97::113u",
+    Event::Key(KeyEvent::from(KeyCode::Char('a')).with_alternate_keys(None, Some(KeyCode::Char('q')))),
+)]
+#[case::kitty_csi_alternate_keys(
+    "\x1b[97:65;2u",
+    Event::Key(
+        KeyEvent::from(KeyCode::Char('a'))
+            .with_modifiers(KeyModifiers::SHIFT)
+            .with_alternate_keys(Some(KeyCode::Char('A')), None)
+    ),
+)]
+// Kitty keyboard protocol with associated text.
+#[case::kitty_csi_associated_text(
+    "\x1b[97;;97:98u",
+    Event::Key(KeyEvent::from(KeyCode::Char('a')).with_text("ab")),
 )]
 // Kitty keyboard protocol with included modifiers into the associated codepoint.
 #[case::kitty_csi_unambiguous_key("\x1b[0;;229u", Event::Key(KeyEvent::from(KeyCode::Char('å'))))]
+// Uppercase chars.
+#[case::uppercase_char(
+    "É",
+    Event::Key(KeyEvent::from(KeyCode::Char('é')).with_modifiers(KeyModifiers::SHIFT))
+)]
+// Kitty keyboard protocol functional keys.
+#[case::kitty_csi_functional_key("\x1b[27u", Event::Key(KeyCode::Escape.into()))]
+#[case::kitty_csi_functional_key("\x1b[13u", Event::Key(KeyCode::Enter.into()))]
+#[case::kitty_csi_functional_key("\x1b[57358u", Event::Key(KeyCode::CapsLock.into()))]
+#[case::kitty_csi_functional_key("\x1b[57361u", Event::Key(KeyCode::PrintScreen.into()))]
+#[case::kitty_csi_functional_key("\x1b[57363u", Event::Key(KeyCode::Menu.into()))]
+#[case::kitty_csi_functional_key("\x1b[57376u", Event::Key(KeyCode::Fn(13).into()))]
+#[case::kitty_csi_functional_key("\x1b[57398u", Event::Key(KeyCode::Fn(35).into()))]
+#[case::kitty_csi_functional_key("\x1b[57399u", Event::Key(KeypadKeyCode::Digit(0).into()))]
+#[case::kitty_csi_functional_key("\x1b[57414u", Event::Key(KeypadKeyCode::Enter.into()))]
+#[case::kitty_csi_functional_key("\x1b[57427~", Event::Key(KeypadKeyCode::Begin.into()))]
+#[case::kitty_csi_functional_key("\x1b[E", Event::Key(KeypadKeyCode::Begin.into()))]
+#[case::kitty_csi_functional_key("\x1b[57430u", Event::Key(MediaKeyCode::PlayPause.into()))]
+#[case::kitty_csi_functional_key(
+    "\x1b[57441;2u",
+    Event::Key(KeyEvent::from(ModifierKeyCode::LeftShift).with_modifiers(KeyModifiers::SHIFT)),
+)]
+#[case::kitty_csi_functional_key(
+    "\x1b[57453u",
+    Event::Key(ModifierKeyCode::IsoLevel3Shift.into()),
+)]
+// Kitty keyboard enhancement flags query reply.
+#[case::kitty_csi_keyboard_enhancement_flags(
+    "\x1b[?0u",
+    Event::KeyboardEnhancementFlags(KeyboardEnhancementFlags::empty())
+)]
+#[case::kitty_csi_keyboard_enhancement_flags(
+    "\x1b[?5u",
+    Event::KeyboardEnhancementFlags(
+        KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+            | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+    ),
+)]
 fn it_should_parse_single_event(#[case] input: &str, #[case] expected: Event) {
     assert_that!(parse(input))
         .is_ok()
@@ -100,3 +193,349 @@ fn it_should_parse_utf8_text(#[case] input: &str, #[case] expected: Event) {
         .map(|(_, second)| second)
         .is_equal_to(expected);
 }
+
+#[rstest]
+#[case::vt100_primary_device_attributes(
+    b"\x1b[?1;2c",
+    TerminalResponse::PrimaryDeviceAttributes {
+        class: 1,
+        attributes: heapless::Vec::from_slice(&[2]).unwrap(),
+    }
+)]
+#[case::vt220_primary_device_attributes(
+    b"\x1b[?62;1;6;22c",
+    TerminalResponse::PrimaryDeviceAttributes {
+        class: 62,
+        attributes: heapless::Vec::from_slice(&[1, 6, 22]).unwrap(),
+    }
+)]
+#[case::primary_device_attributes_with_trailing_separator(
+    b"\x1b[?62;c",
+    TerminalResponse::PrimaryDeviceAttributes { class: 62, attributes: heapless::Vec::new() }
+)]
+#[case::c1_primary_device_attributes(
+    b"\x9b?6c",
+    TerminalResponse::PrimaryDeviceAttributes { class: 6, attributes: heapless::Vec::new() }
+)]
+#[case::secondary_device_attributes(
+    b"\x1b[>41;390;0c",
+    TerminalResponse::SecondaryDeviceAttributes { terminal_type: 41, version: 390, cartridge: 0 }
+)]
+#[case::secondary_device_attributes_without_cartridge(
+    b"\x1b[>1;10c",
+    TerminalResponse::SecondaryDeviceAttributes { terminal_type: 1, version: 10, cartridge: 0 }
+)]
+#[case::version(
+    b"\x1bP>|XTerm(390)\x1b\\",
+    TerminalResponse::Version(heapless::String::try_from("XTerm(390)").unwrap())
+)]
+#[case::c1_version(
+    b"\x90>|kitty(0.35.2)\x9c",
+    TerminalResponse::Version(heapless::String::try_from("kitty(0.35.2)").unwrap())
+)]
+#[case::version_at_capacity(
+    b"\x1bP>|a terminal with a very long name\x07",
+    TerminalResponse::Version(heapless::String::try_from("a terminal with a very long name").unwrap())
+)]
+#[case::truncated_version(
+    b"\x1bP>|a terminal with a very long name, really\x07",
+    TerminalResponse::Version(heapless::String::try_from("a terminal with a very long name").unwrap())
+)]
+fn it_should_parse_terminal_response(#[case] input: &[u8], #[case] expected: TerminalResponse) {
+    let config = ParserConfig::default().with_c1_controls(true);
+
+    assert_that!(parse_bytes_with(input, &config))
+        .is_ok()
+        .is_equal_to((&b""[..], Event::Terminal(expected)));
+}
+
+#[rstest]
+#[case::foreground(
+    b"\x1b]10;rgb:ffff/ffff/ffff\x1b\\",
+    ColorEvent::Foreground(Color::Rgb { r: 255, g: 255, b: 255 })
+)]
+#[case::background_with_bel(
+    b"\x1b]11;rgb:0000/2b2b/3636\x07",
+    ColorEvent::Background(Color::Rgb { r: 0, g: 43, b: 54 })
+)]
+#[case::palette(
+    b"\x1b]4;1;rgb:cdcd/0000/0000\x1b\\",
+    ColorEvent::Palette { index: 1, color: Color::Rgb { r: 205, g: 0, b: 0 } }
+)]
+#[case::short_components(
+    b"\x1b]11;rgb:f/80/abc\x1b\\",
+    ColorEvent::Background(Color::Rgb { r: 255, g: 128, b: 171 })
+)]
+#[case::c1(
+    b"\x9d11;rgb:1a1a/1a1a/1a1a\x9c",
+    ColorEvent::Background(Color::Rgb { r: 26, g: 26, b: 26 })
+)]
+fn it_should_parse_color_event(#[case] input: &[u8], #[case] expected: ColorEvent) {
+    let config = ParserConfig::default().with_c1_controls(true);
+
+    assert_that!(parse_bytes_with(input, &config))
+        .is_ok()
+        .is_equal_to((&b""[..], Event::Color(expected)));
+}
+
+#[test]
+fn it_should_parse_unsupported_color_format_as_unknown_event() {
+    let input = b"\x1b]11;#000000\x1b\\";
+
+    assert_that!(parse_bytes(input))
+        .is_ok()
+        .is_equal_to((&b""[..], Event::Unknown(UnknownEvent::new(input))));
+}
+
+#[rstest]
+#[case::private_set(b"\x1b[?2004;1$y", 2004, true, ModeState::Set)]
+#[case::private_reset(b"\x1b[?2026;2$y", 2026, true, ModeState::Reset)]
+#[case::private_not_recognized(b"\x1b[?2048;0$y", 2048, true, ModeState::NotRecognized)]
+#[case::private_permanently_set(b"\x1b[?7;3$y", 7, true, ModeState::PermanentlySet)]
+#[case::ansi_permanently_reset(b"\x1b[4;4$y", 4, false, ModeState::PermanentlyReset)]
+fn it_should_parse_mode_report(
+    #[case] input: &[u8],
+    #[case] mode: u16,
+    #[case] private: bool,
+    #[case] state: ModeState,
+) {
+    assert_that!(parse_bytes(input)).is_ok().is_equal_to((
+        &b""[..],
+        Event::Mode(ModeReport {
+            mode,
+            private,
+            state,
+        }),
+    ));
+}
+
+#[test]
+fn it_should_parse_mode_report_with_invalid_state_as_unknown_event() {
+    let input = b"\x1b[?2004;5$y";
+
+    assert_that!(parse_bytes(input))
+        .is_ok()
+        .is_equal_to((&b""[..], Event::Unknown(UnknownEvent::new(input))));
+}
+
+#[test]
+fn it_should_wait_for_terminal_version_end() {
+    assert_that!(parse_bytes(b"\x1bP>|XTerm(3"))
+        .is_err_containing(nom::Err::Incomplete(nom::Needed::new(1)));
+}
+
+#[rstest]
+#[case::window_size_report(
+    "\x1b[8;24;80t",
+    ScreenEvent::Resized { width: 80, height: 24, pixel_width: 0, pixel_height: 0 }
+)]
+#[case::in_band_resize(
+    "\x1b[48;24;80;480;640t",
+    ScreenEvent::Resized { width: 80, height: 24, pixel_width: 640, pixel_height: 480 }
+)]
+fn it_should_parse_resize_event(#[case] input: &str, #[case] expected: ScreenEvent) {
+    assert_that!(parse(input))
+        .is_ok()
+        .map(|(_, second)| second)
+        .is_equal_to(Event::Screen(expected));
+}
+
+#[rstest]
+// SGR (1006) encoding.
+#[case::sgr_mouse(
+    "\x1b[<0;10;5M",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Down(MouseButton::Left), 9, 4))
+)]
+#[case::sgr_mouse(
+    "\x1b[<2;10;5m",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Up(MouseButton::Right), 9, 4))
+)]
+#[case::sgr_mouse(
+    "\x1b[<33;1;1M",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Drag(MouseButton::Middle), 0, 0))
+)]
+#[case::sgr_mouse(
+    "\x1b[<35;120;40M",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Moved, 119, 39))
+)]
+#[case::sgr_mouse(
+    "\x1b[<65;3;4M",
+    Event::Mouse(MouseEvent::new(MouseEventKind::ScrollDown, 2, 3))
+)]
+#[case::sgr_mouse(
+    "\x1b[<20;3;4M",
+    Event::Mouse(
+        MouseEvent::new(MouseEventKind::Down(MouseButton::Left), 2, 3)
+            .with_modifiers(KeyModifiers::SHIFT | KeyModifiers::CONTROL)
+    ),
+)]
+// Legacy X10 encoding.
+#[case::x10_mouse(
+    "\x1b[M !!",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Down(MouseButton::Left), 0, 0))
+)]
+#[case::x10_mouse(
+    "\x1b[M#*%",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Up(MouseButton::Left), 9, 4))
+)]
+#[case::x10_mouse(
+    "\x1b[M`!!",
+    Event::Mouse(MouseEvent::new(MouseEventKind::ScrollUp, 0, 0))
+)]
+// urxvt (1015) encoding.
+#[case::urxvt_mouse(
+    "\x1b[32;10;5M",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Down(MouseButton::Left), 9, 4))
+)]
+#[case::urxvt_mouse(
+    "\x1b[43;10;5M",
+    Event::Mouse(
+        MouseEvent::new(MouseEventKind::Up(MouseButton::Left), 9, 4)
+            .with_modifiers(KeyModifiers::ALT)
+    ),
+)]
+fn it_should_parse_mouse_event(#[case] input: &str, #[case] expected: Event) {
+    assert_that!(parse(input))
+        .is_ok()
+        .map(|(_, second)| second)
+        .is_equal_to(expected);
+}
+
+#[rstest]
+#[case::bracketed_paste("\x1b[200~hello\x1b[201~", "hello")]
+#[case::bracketed_paste("\x1b[200~\x1b[Aa\rb\x1b[201~", "\x1b[Aa\rb")]
+// `Û` is `C3 9B`, whose last byte looks like an 8-bit CSI.
+#[case::bracketed_paste("\x1b[200~aÛ201~b\x1b[201~", "aÛ201~b")]
+fn it_should_parse_bracketed_paste(#[case] input: &str, #[case] expected: &str) {
+    assert_that!(parse(input))
+        .is_ok()
+        .map(|(_, second)| second.as_paste_event().unwrap())
+        .matches(|paste| paste.as_str() == expected && !paste.is_partial());
+}
+
+#[test]
+fn it_should_wait_for_bracketed_paste_end() {
+    assert_that!(parse("\x1b[200~hello\x1b[20"))
+        .is_err_containing(nom::Err::Incomplete(nom::Needed::Unknown));
+}
+
+#[rstest]
+#[case::two_bytes(b"\xc3\xa9", 'é')]
+#[case::three_bytes(b"\xe2\x82\xac", '€')]
+#[case::four_bytes(b"\xf0\x9f\xa6\x80", '🦀')]
+fn it_should_parse_utf8_bytes(#[case] input: &[u8], #[case] expected: char) {
+    assert_that!(parse_bytes(input))
+        .is_ok()
+        .map(|(_, second)| second)
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char(expected))));
+}
+
+#[rstest]
+#[case::two_bytes(b"\xc3")]
+#[case::three_bytes(b"\xe2\x82")]
+#[case::four_bytes(b"\xf0\x9f\xa6")]
+fn it_should_wait_for_partial_utf8_bytes(#[case] input: &[u8]) {
+    assert_that!(parse_bytes(input).map_err(|err| err.is_incomplete())).is_err_containing(true);
+}
+
+#[rstest]
+#[case::continuation_byte(b"\x80a")]
+#[case::invalid_byte(b"\xffa")]
+#[case::truncated_char(b"\xc3a")]
+fn it_should_fail_on_invalid_utf8_bytes(#[case] input: &[u8]) {
+    assert_that!(parse_bytes(input))
+        .is_err()
+        .matches(|err| matches!(err, nom::Err::Failure(_)));
+}
+
+#[rstest]
+#[case::c1_ss3_arrow_keys(b"\x8fA", Event::Key(KeyCode::Up.into()))]
+#[case::c1_ss3_function_keys(b"\x8fP", Event::Key(KeyCode::Fn(1).into()))]
+#[case::c1_csi_arrow_keys(b"\x9bA", Event::Key(KeyCode::Up.into()))]
+#[case::c1_csi_function_keys(b"\x9b15~", Event::Key(KeyCode::Fn(5).into()))]
+#[case::c1_csi_keys_with_modifiers(
+    b"\x9b97;5u",
+    Event::Key(KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::CONTROL))
+)]
+#[case::c1_csi_mouse(
+    b"\x9b<0;10;5M",
+    Event::Mouse(MouseEvent::new(MouseEventKind::Down(MouseButton::Left), 9, 4))
+)]
+#[case::c1_bracketed_paste(
+    b"\x9b200~hello\x9b201~",
+    Event::Paste(crate::events::PasteEvent { content: "hello".try_into().unwrap(), partial: false })
+)]
+#[case::c0_csi_arrow_keys(b"\x1b[A", Event::Key(KeyCode::Up.into()))]
+fn it_should_parse_c1_controls(#[case] input: &[u8], #[case] expected: Event) {
+    let config = ParserConfig::default().with_c1_controls(true);
+
+    assert_that!(parse_bytes_with(input, &config))
+        .is_ok()
+        .map(|(_, second)| second)
+        .is_equal_to(expected);
+}
+
+#[rstest]
+#[case::c1_ss3(b"\x8fA")]
+#[case::c1_csi(b"\x9bA")]
+#[case::c1_dcs_version(b"\x90>|kitty(0.35.2)\x9c")]
+#[case::c1_osc_color(b"\x9d11;rgb:1a1a/1a1a/1a1a\x9c")]
+fn it_should_fail_on_c1_controls_by_default(#[case] input: &[u8]) {
+    assert_that!(parse_bytes(input))
+        .is_err()
+        .matches(|err| matches!(err, nom::Err::Failure(_)));
+}
+
+#[test]
+fn it_should_reject_c1_replies_by_default() {
+    let config = ParserConfig::default();
+
+    assert_that!(parser::parse_xterm_dcs_version_escape_code(
+        b"\x90>|kitty(0.35.2)\x9c",
+        &config
+    ))
+    .is_err();
+    assert_that!(parser::parse_xterm_osc_color_escape_code(
+        b"\x9d11;rgb:1a1a/1a1a/1a1a\x9c",
+        &config
+    ))
+    .is_err();
+}
+
+#[rstest]
+#[case::csi(b"\x1b[5;1;2z", b"\x1b[5;1;2z", b"")]
+#[case::csi_with_intermediate(b"\x1b[0 q", b"\x1b[0 q", b"")]
+#[case::interrupted_csi(b"\x1b[12\x1b[A", b"\x1b[12", b"\x1b[A")]
+#[case::ss3(b"\x1bOxa", b"\x1bOx", b"a")]
+#[case::osc_with_bel(b"\x1b]52;c;aGk=\x07a", b"\x1b]52;c;aGk=\x07", b"a")]
+#[case::osc_with_st(b"\x1b]52;c;aGk=\x1b\\a", b"\x1b]52;c;aGk=\x1b\\", b"a")]
+#[case::dcs(b"\x1bP1$r0m\x1b\\", b"\x1bP1$r0m\x1b\\", b"")]
+fn it_should_parse_unknown_sequence(
+    #[case] input: &[u8],
+    #[case] expected: &[u8],
+    #[case] remaining: &[u8],
+) {
+    assert_that!(parse_bytes(input))
+        .is_ok()
+        .is_equal_to((remaining, Event::Unknown(UnknownEvent::new(expected))));
+}
+
+#[test]
+fn it_should_truncate_long_unknown_sequence() {
+    let input = b"\x1b]0;a very long window title that does not fit\x07";
+
+    assert_that!(parse_bytes(input))
+        .is_ok()
+        .map(|(_, second)| second.as_unknown_event().unwrap())
+        .matches(|event| {
+            event.is_truncated() && event.as_bytes() == &input[..crate::events::UNKNOWN_CAPACITY]
+        });
+}
+
+#[rstest]
+#[case::csi(b"\x1b[5;1")]
+#[case::osc(b"\x1b]52;c")]
+#[case::dcs_before_st(b"\x1bP1$r0m\x1b")]
+fn it_should_wait_for_unknown_sequence_end(#[case] input: &[u8]) {
+    assert_that!(parse_bytes(input).map_err(|err| err.is_incomplete())).is_err_containing(true);
+}
@@ -0,0 +1,77 @@
+//! Blocking event reader.
+
+use crate::events::Event;
+use crate::events::decoder::{
+    DEFAULT_BUFFER_SIZE, DEFAULT_QUEUE_SIZE, DecoderConfig, EventDecoder,
+};
+use crate::io;
+
+#[cfg(test)]
+mod tests;
+
+/// Blocking reader of events, which behaves as an event stream without escape timeout.
+#[derive(Debug)]
+pub struct EventReader<
+    'a,
+    ReaderTy,
+    const BUFFER_SIZE: usize = DEFAULT_BUFFER_SIZE,
+    const QUEUE_SIZE: usize = DEFAULT_QUEUE_SIZE,
+> {
+    reader: &'a mut ReaderTy,
+    decoder: EventDecoder<BUFFER_SIZE, QUEUE_SIZE>,
+}
+
+impl<'a, ReaderTy> EventReader<'a, ReaderTy> {
+    pub fn new(reader: &'a mut ReaderTy) -> Self {
+        Self::with_config(reader, DecoderConfig::new())
+    }
+}
+
+impl<'a, ReaderTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>
+    EventReader<'a, ReaderTy, BUFFER_SIZE, QUEUE_SIZE>
+{
+    /// Create a reader with the given config.
+    pub fn with_config(
+        reader: &'a mut ReaderTy,
+        config: DecoderConfig<BUFFER_SIZE, QUEUE_SIZE>,
+    ) -> Self {
+        Self {
+            reader,
+            decoder: EventDecoder::with_config(config),
+        }
+    }
+}
+
+impl<ReaderTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize> Iterator
+    for EventReader<'_, ReaderTy, BUFFER_SIZE, QUEUE_SIZE>
+where
+    ReaderTy: io::blocking::Read,
+{
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.decoder.next_event() {
+                return Some(event);
+            }
+
+            match self.reader.read(self.decoder.spare_mut()) {
+                Ok(0) if self.decoder.finish() => {}
+                Ok(0) => return None,
+                Ok(count) => self.decoder.commit(count),
+                Err(err) => {
+                    self.decoder.reset();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Read events, blocking until each of them is available.
+pub fn read<ReaderTy>(reader: &mut ReaderTy) -> EventReader<'_, ReaderTy>
+where
+    ReaderTy: io::blocking::Read,
+{
+    EventReader::new(reader)
+}
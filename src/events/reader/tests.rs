@@ -0,0 +1,127 @@
+use rstest::rstest;
+use speculoos::prelude::*;
+
+use crate::events::{
+    self, DecoderConfig, Event, EventReader, KeyCode, KeyEvent, ParseError, PasteEvent,
+    UnknownEvent,
+};
+use crate::io;
+
+/// Reader that yields the given chunks, at most one per read.
+struct Chunks(std::collections::VecDeque<&'static [u8]>);
+
+impl Chunks {
+    fn new(chunks: &[&'static [u8]]) -> Self {
+        Self(chunks.iter().copied().collect())
+    }
+}
+
+impl io::blocking::Read for Chunks {
+    fn read(&mut self, data: &mut [u8]) -> io::Result<usize> {
+        let Some(chunk) = self.0.pop_front() else {
+            return Ok(0);
+        };
+
+        let n = chunk.len().min(data.len());
+        data[..n].copy_from_slice(&chunk[..n]);
+
+        if n < chunk.len() {
+            self.0.push_front(&chunk[n..]);
+        }
+
+        Ok(n)
+    }
+}
+
+/// Reader that fails once its input is exhausted.
+struct FailAfter(Chunks);
+
+impl io::blocking::Read for FailAfter {
+    fn read(&mut self, data: &mut [u8]) -> io::Result<usize> {
+        match self.0.read(data)? {
+            0 => Err(io::Error::UnexpectedEof),
+            n => Ok(n),
+        }
+    }
+}
+
+#[test]
+fn it_should_read_empty_events() {
+    let mut input = Chunks::new(&[]);
+
+    assert_that!(events::read(&mut input).next()).is_none();
+}
+
+#[test]
+fn it_should_read_events_split_across_reads() {
+    let mut input = Chunks::new(&[b"a\x1b[", b"1;5", b"2z\x1bO", b"Pb"]);
+
+    let events = events::read(&mut input).collect::<Vec<_>>();
+
+    assert_that!(events).is_equal_to(vec![
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('a')))),
+        Ok(Event::Unknown(UnknownEvent::new(b"\x1b[1;52z"))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Fn(1)))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('b')))),
+    ]);
+}
+
+#[test]
+fn it_should_read_bracketed_paste_with_config() {
+    let mut input = Chunks::new(&[b"\x1b[200~hello\r\nworld\x1b[201~\r"]);
+
+    let config = DecoderConfig::new().with_buffer_size::<16>();
+    let mut reader = EventReader::with_config(&mut input, config);
+
+    assert_that!(reader.next())
+        .is_some()
+        .is_ok()
+        .map(|event| event.as_paste_event().unwrap())
+        .matches(|paste| paste.as_str() == "hello\r\nworld" && !paste.is_partial());
+
+    assert_that!(reader.next())
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Enter)));
+}
+
+#[test]
+fn it_should_read_error_and_reset() {
+    let mut input = FailAfter(Chunks::new(&[b"a\x1b["]));
+
+    let mut reader = events::read(&mut input);
+
+    assert_that!(reader.next())
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+
+    assert_that!(reader.next())
+        .is_some()
+        .is_err_containing(io::Error::UnexpectedEof);
+}
+
+#[rstest]
+#[case::lone_escape(b"a\x1b", Ok(Event::Key(KeyCode::Escape.into())))]
+#[case::partial_sequence(
+    b"a\x1b[1;",
+    Ok(Event::Unknown(UnknownEvent { truncated: true, ..UnknownEvent::new(b"\x1b[1;") })),
+)]
+#[case::partial_char(b"a\xc3", Err(ParseError::new(b"\xc3").into()))]
+#[case::unterminated_paste(
+    b"a\x1b[200~hi\x1b[20",
+    Ok(Event::Paste(PasteEvent { content: "hi".try_into().unwrap(), partial: true })),
+)]
+fn it_should_read_pending_input_at_end(
+    #[case] input: &'static [u8],
+    #[case] expected: io::Result<Event>,
+) {
+    let mut input = Chunks::new(&[input]);
+
+    let events = events::read(&mut input).collect::<Vec<_>>();
+
+    assert_that!(events).is_equal_to(vec![
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('a')))),
+        expected,
+    ]);
+}
@@ -1,102 +1,464 @@
 //! Event stream.
 
-use futures::Stream;
-use heapless::Deque;
+use core::ops::ControlFlow;
+use core::time::Duration;
 
-use crate::events::{self, Event};
+use futures::future::{Either, select};
+use futures::{Stream, pin_mut};
+
+use crate::cursor::{GetPosition, MoveTo, RestorePosition, SavePosition};
+use crate::events::decoder::{
+    DEFAULT_BUFFER_SIZE, DEFAULT_QUEUE_SIZE, DecoderConfig, EventDecoder, EventQueue,
+    OverflowPolicy,
+};
+use crate::events::{
+    ColorEvent, CursorEvent, Event, KeyboardEnhancementFlags, ModeState, ParserConfig, ScreenEvent,
+};
 use crate::io;
+use crate::keyboard::QueryKeyboardEnhancementFlags;
+use crate::style::Color;
+use crate::terminal::{QueryBackgroundColor, QuerySize, RequestMode, Size};
+use crate::time::{Delay, Never};
+use crate::{Command, Executable, Queuable};
 
 #[cfg(test)]
 mod tests;
 
+/// Stream config, i.e. a decoder config along with an escape timeout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StreamConfig<
+    const BUFFER_SIZE: usize = DEFAULT_BUFFER_SIZE,
+    const QUEUE_SIZE: usize = DEFAULT_QUEUE_SIZE,
+> {
+    decoder: DecoderConfig<BUFFER_SIZE, QUEUE_SIZE>,
+    escape_timeout: Option<Duration>,
+}
+
+impl StreamConfig {
+    /// Create a config with default buffer and queue sizes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<const BUFFER_SIZE: usize, const QUEUE_SIZE: usize> StreamConfig<BUFFER_SIZE, QUEUE_SIZE> {
+    /// Decode events with the given config.
+    pub fn with_decoder_config<const BUFFER: usize, const QUEUE: usize>(
+        self,
+        decoder: DecoderConfig<BUFFER, QUEUE>,
+    ) -> StreamConfig<BUFFER, QUEUE> {
+        StreamConfig {
+            decoder,
+            escape_timeout: self.escape_timeout,
+        }
+    }
+
+    /// Set the size in bytes of the input buffer, see [`DecoderConfig`].
+    pub fn with_buffer_size<const SIZE: usize>(self) -> StreamConfig<SIZE, QUEUE_SIZE> {
+        self.with_decoder_config(self.decoder.with_buffer_size::<SIZE>())
+    }
+
+    /// Set the maximum number of pending events, see [`DecoderConfig`].
+    pub fn with_queue_size<const SIZE: usize>(self) -> StreamConfig<BUFFER_SIZE, SIZE> {
+        self.with_decoder_config(self.decoder.with_queue_size::<SIZE>())
+    }
+
+    /// Parse events with the given parser config.
+    pub fn with_parser_config(mut self, parser: ParserConfig) -> Self {
+        self.decoder = self.decoder.with_parser_config(parser);
+        self
+    }
+
+    /// Report a lone escape byte as [`KeyCode::Escape`] once no continuation byte arrived within
+    /// the given timeout.
+    ///
+    /// [`KeyCode::Escape`]: crate::events::KeyCode::Escape
+    pub fn with_escape_timeout(mut self, timeout: Duration) -> Self {
+        self.escape_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the behavior when the queue of pending events is full.
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.decoder = self.decoder.with_overflow_policy(policy);
+        self
+    }
+
+    pub fn decoder_config(&self) -> &DecoderConfig<BUFFER_SIZE, QUEUE_SIZE> {
+        &self.decoder
+    }
+
+    pub fn parser_config(&self) -> &ParserConfig {
+        self.decoder.parser_config()
+    }
+
+    pub fn escape_timeout(&self) -> Option<Duration> {
+        self.escape_timeout
+    }
+
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.decoder.overflow_policy()
+    }
+}
+
+/// Async reader of events, on top of which terminal queries are made.
+///
+/// Events that arrive while waiting for the reply to a query are kept, and then read in order
+/// once the query completes.
 #[derive(Debug)]
-struct Context<'a, ReaderTy> {
+pub struct AsyncEventReader<
+    'a,
+    ReaderTy,
+    DelayTy,
+    const BUFFER_SIZE: usize = DEFAULT_BUFFER_SIZE,
+    const QUEUE_SIZE: usize = DEFAULT_QUEUE_SIZE,
+> {
     reader: &'a mut ReaderTy,
-    buffer: [u8; 32],
-    rpos: usize,
-    wpos: usize,
-    queue: Deque<io::Result<Event>, 32>,
+    delay: DelayTy,
+    escape_timeout: Option<Duration>,
+    decoder: EventDecoder<BUFFER_SIZE, QUEUE_SIZE>,
+    deferred: EventQueue<QUEUE_SIZE>,
+}
+
+impl<'a, ReaderTy, DelayTy> AsyncEventReader<'a, ReaderTy, DelayTy> {
+    /// Create a reader whose delay bounds the time spent waiting for replies to queries.
+    pub fn new(reader: &'a mut ReaderTy, delay: DelayTy) -> Self {
+        Self::with_config(reader, delay, StreamConfig::new())
+    }
 }
 
-impl<'a, ReaderTy> Context<'a, ReaderTy> {
-    fn new(reader: &'a mut ReaderTy) -> Self {
-        Context {
+impl<'a, ReaderTy, DelayTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>
+    AsyncEventReader<'a, ReaderTy, DelayTy, BUFFER_SIZE, QUEUE_SIZE>
+{
+    /// Create a reader with the given config.
+    pub fn with_config(
+        reader: &'a mut ReaderTy,
+        delay: DelayTy,
+        config: StreamConfig<BUFFER_SIZE, QUEUE_SIZE>,
+    ) -> Self {
+        Self {
             reader,
-            buffer: Default::default(),
-            rpos: 0,
-            wpos: 0,
-            queue: Default::default(),
+            delay,
+            escape_timeout: config.escape_timeout,
+            decoder: EventDecoder::with_config(config.decoder),
+            // Events kept during queries must never be dropped, as the reader cannot tell.
+            deferred: EventQueue::new(OverflowPolicy::Backpressure),
         }
     }
 }
 
-pub fn stream<ReaderTy>(reader: &mut ReaderTy) -> impl Stream<Item = io::Result<Event>>
+impl<ReaderTy, DelayTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>
+    AsyncEventReader<'_, ReaderTy, DelayTy, BUFFER_SIZE, QUEUE_SIZE>
 where
-    ReaderTy: io::Read + Send,
+    ReaderTy: io::Read,
+    DelayTy: Delay,
 {
-    futures::stream::unfold(Context::new(reader), |mut cx| async move {
+    /// Read the next event, or `None` once the reader reached its end.
+    pub async fn next_event(&mut self) -> Option<io::Result<Event>> {
+        if let Some(event) = self.deferred.pop() {
+            return Some(event);
+        }
+
         loop {
-            // We start by purging the queue of pending events in order to preserve causality.
-            if let Some(event) = cx.queue.pop_front() {
-                return Some((event, cx));
+            if let Some(event) = self.decoder.next_event() {
+                return Some(event);
             }
 
-            let mut byte = [0u8; 1];
+            let result = match self.escape_timeout {
+                Some(timeout) if self.decoder.is_escape_pending() => {
+                    let read = self.reader.read(self.decoder.spare_mut());
+                    let delay = self.delay.delay(timeout);
+                    pin_mut!(read, delay);
 
-            let nbytes = match cx.reader.read(&mut byte).await {
-                Ok(n) => n,
-                Err(err) => return Some((Err(err), Context::new(cx.reader))),
+                    match select(read, delay).await {
+                        Either::Left((result, _)) => Some(result),
+                        Either::Right(_) => None,
+                    }
+                }
+                _ => Some(self.reader.read(self.decoder.spare_mut()).await),
+            };
+
+            // No continuation byte arrived in time, so that the escape byte stands on its own.
+            let Some(result) = result else {
+                self.decoder.flush_escape();
+                continue;
             };
 
-            if 0 == nbytes {
-                return None;
+            match result {
+                Ok(0) if self.decoder.finish() => {}
+                Ok(0) => return None,
+                Ok(count) => self.decoder.commit(count),
+                Err(err) => {
+                    self.decoder.reset();
+                    return Some(Err(err));
+                }
             }
+        }
+    }
+
+    /// Execute the given command and wait for its reply, i.e. the first event for which the given
+    /// function returns something.
+    ///
+    /// Other events are kept to be read later on. It fails with [`io::Error::TimedOut`] if no reply
+    /// arrived within the given timeout, in which case the pending read is dropped, so that the
+    /// reader must be cancel-safe.
+    pub async fn query<WriterTy, CommandTy, ReplyTy>(
+        &mut self,
+        writer: &mut WriterTy,
+        command: CommandTy,
+        timeout: Duration,
+        mut reply: impl FnMut(&Event) -> Option<ReplyTy>,
+    ) -> io::Result<ReplyTy>
+    where
+        WriterTy: io::blocking::Write,
+        CommandTy: Command,
+    {
+        self.query_until(writer, command, timeout, |event| {
+            reply(event).map(ControlFlow::Break)
+        })
+        .await
+    }
 
-            cx.buffer[cx.wpos] = byte[0];
-            cx.wpos += 1;
+    /// Execute the given command and consume its replies until the given function breaks.
+    ///
+    /// The function tells whether it consumed the event, in which case it either continues or
+    /// breaks with the final reply, or not, in which case the event is kept to be read later on.
+    /// It allows to wait for several replies at once, e.g. replies to queries queued beforehand.
+    /// Timeout is handled as in [`query`](Self::query).
+    ///
+    /// Kept events are never dropped, whatever the overflow policy. Once the queue is full of them,
+    /// input is no longer read and the query fails early with [`io::Error::BufferFull`], so that
+    /// its reply, if any, is read later on as a regular event.
+    pub async fn query_until<WriterTy, CommandTy, ReplyTy>(
+        &mut self,
+        writer: &mut WriterTy,
+        command: CommandTy,
+        timeout: Duration,
+        mut consume: impl FnMut(&Event) -> Option<ControlFlow<ReplyTy>>,
+    ) -> io::Result<ReplyTy>
+    where
+        WriterTy: io::blocking::Write,
+        CommandTy: Command,
+    {
+        writer.execute(command)?;
 
-            let Ok(mut input) = str::from_utf8(&cx.buffer[cx.rpos..cx.wpos]) else {
-                return Some((Err(io::Error::Unknown), Context::new(cx.reader)));
+        let deadline = self.delay.delay(timeout);
+        pin_mut!(deadline);
+
+        loop {
+            if self.deferred.is_blocked() {
+                return Err(io::Error::BufferFull);
+            }
+
+            let read = read_event(self.reader, &mut self.decoder);
+            pin_mut!(read);
+
+            let event = match select(read, deadline.as_mut()).await {
+                Either::Left((result, _)) => result?,
+                Either::Right(_) => return Err(io::Error::TimedOut),
             };
 
-            loop {
-                if input.is_empty() {
-                    cx.rpos = 0;
-                    cx.wpos = 0;
-                    break;
-                }
+            match event {
+                Some(Ok(event)) => match consume(&event) {
+                    Some(ControlFlow::Break(reply)) => return Ok(reply),
+                    Some(ControlFlow::Continue(())) => {}
+                    None => self.deferred.push(Ok(event)),
+                },
+                Some(Err(err)) => self.deferred.push(Err(err)),
+                None => return Err(io::Error::UnexpectedEof),
+            }
+        }
+    }
 
-                match events::parse(input) {
-                    Ok((rest, event)) => {
-                        cx.rpos += input.len() - rest.len();
-                        input = rest;
+    /// Query the cursor position, i.e. `(x, y)` as reported by the terminal, which is one-based.
+    pub async fn query_cursor_position<WriterTy>(
+        &mut self,
+        writer: &mut WriterTy,
+        timeout: Duration,
+    ) -> io::Result<(u16, u16)>
+    where
+        WriterTy: io::blocking::Write,
+    {
+        self.query(writer, GetPosition, timeout, |event| match event {
+            Event::Cursor(CursorEvent::Positioned { x, y }) => Some((*x, *y)),
+            _ => None,
+        })
+        .await
+    }
 
-                        if cx.queue.is_full() {
-                            cx.queue.pop_front();
-                        }
+    /// Query the size of the terminal.
+    ///
+    /// It first queries the size of the text area, i.e. `CSI 18 t`, which not all terminals
+    /// support. Otherwise, it moves the cursor as far as possible to the bottom right, i.e. to
+    /// `999;999`, queries its position and then restores it. The timeout applies to each query.
+    pub async fn query_size<WriterTy>(
+        &mut self,
+        writer: &mut WriterTy,
+        timeout: Duration,
+    ) -> io::Result<Size>
+    where
+        WriterTy: io::blocking::Write,
+    {
+        let reply = self
+            .query(writer, QuerySize, timeout, |event| match event {
+                Event::Screen(ScreenEvent::Resized { width, height, .. }) => Some(Size {
+                    rows: *height,
+                    columns: *width,
+                }),
+                _ => None,
+            })
+            .await;
 
-                        // SAFETY: we can ensure that the queue is not full thanks to the lines
-                        // above.
-                        unsafe { cx.queue.push_back_unchecked(Ok(event)) }
-                    }
+        if !matches!(reply, Err(io::Error::TimedOut)) {
+            return reply;
+        }
 
-                    Err(nom::Err::Incomplete(_)) => break,
-                    Err(nom::Err::Error(_)) => break,
+        writer.queue(SavePosition)?.queue(MoveTo(998, 998))?;
 
-                    // Unrecoverable error, then reset the context.
-                    Err(nom::Err::Failure(_)) => {
-                        if cx.queue.is_full() {
-                            cx.queue.pop_front();
-                        }
+        let position = self.query_cursor_position(writer, timeout).await;
 
-                        unsafe { cx.queue.push_back_unchecked(Err(io::Error::Unknown)) };
+        writer.execute(RestorePosition)?;
 
-                        cx.rpos = 0;
-                        cx.wpos = 0;
-                        break;
-                    }
-                }
+        let (columns, rows) = position?;
+        Ok(Size { rows, columns })
+    }
+
+    /// Query the enabled keyboard enhancement flags.
+    pub async fn query_keyboard_enhancement_flags<WriterTy>(
+        &mut self,
+        writer: &mut WriterTy,
+        timeout: Duration,
+    ) -> io::Result<KeyboardEnhancementFlags>
+    where
+        WriterTy: io::blocking::Write,
+    {
+        self.query(
+            writer,
+            QueryKeyboardEnhancementFlags,
+            timeout,
+            |event| match event {
+                Event::KeyboardEnhancementFlags(flags) => Some(*flags),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Query the default background color, e.g. in order to tell dark and light color schemes apart
+    /// with [`ColorScheme::from_background`](crate::style::ColorScheme::from_background).
+    pub async fn query_background_color<WriterTy>(
+        &mut self,
+        writer: &mut WriterTy,
+        timeout: Duration,
+    ) -> io::Result<Color>
+    where
+        WriterTy: io::blocking::Write,
+    {
+        self.query(writer, QueryBackgroundColor, timeout, |event| match event {
+            Event::Color(ColorEvent::Background(color)) => Some(*color),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Query the state of a mode.
+    pub async fn query_mode<WriterTy>(
+        &mut self,
+        writer: &mut WriterTy,
+        request: RequestMode,
+        timeout: Duration,
+    ) -> io::Result<ModeState>
+    where
+        WriterTy: io::blocking::Write,
+    {
+        let RequestMode(mode, private) = request;
+
+        self.query(writer, request, timeout, |event| match event {
+            Event::Mode(report) if report.mode == mode && report.private == private => {
+                Some(report.state)
+            }
+            _ => None,
+        })
+        .await
+    }
+
+    /// Turn into a stream of events.
+    pub fn into_stream(self) -> impl Stream<Item = io::Result<Event>> {
+        futures::stream::unfold(self, |mut reader| async move {
+            let event = reader.next_event().await?;
+            Some((event, reader))
+        })
+    }
+}
+
+/// Read the next event without escape timeout, failing on read error only.
+async fn read_event<ReaderTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>(
+    reader: &mut ReaderTy,
+    decoder: &mut EventDecoder<BUFFER_SIZE, QUEUE_SIZE>,
+) -> io::Result<Option<io::Result<Event>>>
+where
+    ReaderTy: io::Read,
+{
+    loop {
+        if let Some(event) = decoder.next_event() {
+            return Ok(Some(event));
+        }
+
+        match reader.read(decoder.spare_mut()).await {
+            Ok(0) if decoder.finish() => {}
+            Ok(0) => return Ok(None),
+            Ok(count) => decoder.commit(count),
+            Err(err) => {
+                decoder.reset();
+                return Err(err);
             }
         }
-    })
+    }
+}
+
+pub fn stream<ReaderTy>(reader: &mut ReaderTy) -> impl Stream<Item = io::Result<Event>>
+where
+    ReaderTy: io::Read + Send,
+{
+    AsyncEventReader::new(reader, Never).into_stream()
+}
+
+/// Stream events, reporting a lone escape byte as [`KeyCode::Escape`] once no continuation byte
+/// arrived within the given timeout.
+///
+/// Without such a timeout, pressing the escape key is only reported along with the next key,
+/// which is then seen as modified by ALT. Please note that the pending read is dropped when the
+/// timeout elapses, so that the reader must be cancel-safe.
+///
+/// [`KeyCode::Escape`]: crate::events::KeyCode::Escape
+pub fn stream_with_escape_timeout<ReaderTy, DelayTy>(
+    reader: &mut ReaderTy,
+    delay: DelayTy,
+    timeout: Duration,
+) -> impl Stream<Item = io::Result<Event>>
+where
+    ReaderTy: io::Read + Send,
+    DelayTy: Delay + Send,
+{
+    stream_with_config(
+        reader,
+        delay,
+        StreamConfig::new().with_escape_timeout(timeout),
+    )
+}
+
+/// Stream events with the given config.
+///
+/// The delay is only used if an escape timeout is set, see [`stream_with_escape_timeout`].
+pub fn stream_with_config<ReaderTy, DelayTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>(
+    reader: &mut ReaderTy,
+    delay: DelayTy,
+    config: StreamConfig<BUFFER_SIZE, QUEUE_SIZE>,
+) -> impl Stream<Item = io::Result<Event>>
+where
+    ReaderTy: io::Read + Send,
+    DelayTy: Delay + Send,
+{
+    AsyncEventReader::with_config(reader, delay, config).into_stream()
 }
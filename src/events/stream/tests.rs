@@ -1,7 +1,18 @@
+use core::time::Duration;
+
 use futures::{StreamExt, pin_mut};
+use rstest::rstest;
 use speculoos::prelude::*;
 
-use crate::events::{self, Event, KeyCode, KeyEvent};
+use crate::events::{
+    self, AsyncEventReader, CursorEvent, Event, KeyCode, KeyEvent, KeyModifiers, ModeReport,
+    ModeState, OverflowPolicy, PASTE_CAPACITY, ParseError, ParserConfig, ScreenEvent, StreamConfig,
+    UnknownEvent,
+};
+use crate::style::Color;
+use crate::terminal::{RequestMode, Size, Terminal};
+use crate::time::Delay;
+use crate::time::Never;
 
 impl crate::io::Read for String {
     async fn read(&mut self, data: &mut [u8]) -> crate::io::Result<usize> {
@@ -9,10 +20,105 @@ impl crate::io::Read for String {
         let (input, _) = self.as_bytes().split_at(n);
         let (output, _) = data.split_at_mut(n);
         output.copy_from_slice(input);
+        self.drain(..n);
+        Ok(n)
+    }
+}
+
+impl crate::io::Read for Vec<u8> {
+    async fn read(&mut self, data: &mut [u8]) -> crate::io::Result<usize> {
+        let n = self.len().min(data.len());
+        data[..n].copy_from_slice(&self[..n]);
+        self.drain(..n);
+        Ok(n)
+    }
+}
+
+/// Reader that yields the given chunks, at most one per read, and counts its reads.
+struct Chunks {
+    chunks: std::collections::VecDeque<&'static [u8]>,
+    reads: usize,
+}
+
+impl Chunks {
+    fn new(chunks: &[&'static [u8]]) -> Self {
+        Self {
+            chunks: chunks.iter().copied().collect(),
+            reads: 0,
+        }
+    }
+}
+
+impl crate::io::Read for Chunks {
+    async fn read(&mut self, data: &mut [u8]) -> crate::io::Result<usize> {
+        self.reads += 1;
+
+        let Some(chunk) = self.chunks.pop_front() else {
+            return Ok(0);
+        };
+
+        let n = chunk.len().min(data.len());
+        data[..n].copy_from_slice(&chunk[..n]);
+
+        if n < chunk.len() {
+            self.chunks.push_front(&chunk[n..]);
+        }
+
         Ok(n)
     }
 }
 
+/// Reader that waits forever once its input is exhausted, like an idle serial line.
+struct IdleAfter(String);
+
+impl crate::io::Read for IdleAfter {
+    async fn read(&mut self, data: &mut [u8]) -> crate::io::Result<usize> {
+        if self.0.is_empty() {
+            futures::future::pending::<()>().await;
+        }
+        self.0.read(data).await
+    }
+}
+
+/// Terminal that only replies to cursor position queries, with the cursor at the bottom right of a
+/// 80x24 screen.
+#[derive(Clone, Default)]
+struct Vt100(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl crate::io::blocking::Write for Vt100 {
+    fn write(&mut self, data: &[u8]) -> crate::io::Result<usize> {
+        if data == b"\x1b[6n" {
+            self.0.borrow_mut().extend_from_slice(b"\x1b[24;80R");
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> crate::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl crate::io::Read for Vt100 {
+    async fn read(&mut self, data: &mut [u8]) -> crate::io::Result<usize> {
+        if self.0.borrow().is_empty() {
+            futures::future::pending::<()>().await;
+        }
+        let mut replies = self.0.borrow_mut();
+        let n = replies.len().min(data.len());
+        data[..n].copy_from_slice(&replies[..n]);
+        replies.drain(..n);
+        Ok(n)
+    }
+}
+
+struct Sleep;
+
+impl Delay for Sleep {
+    async fn delay(&mut self, duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+}
+
 #[tokio::test]
 async fn it_should_stream_empty_events() {
     let mut input = String::from("");
@@ -35,3 +141,555 @@ async fn it_should_stream_single_key_event() {
         .is_ok()
         .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Enter)));
 }
+
+#[tokio::test]
+async fn it_should_stream_bracketed_paste_as_single_event() {
+    let mut input = String::from("\x1b[200~hello\r\nworld\x1b[201~\x0d");
+
+    let stream = events::stream(&mut input);
+    pin_mut!(stream);
+
+    let event = stream.next().await;
+    assert_that!(event)
+        .is_some()
+        .is_ok()
+        .map(|event| event.as_paste_event().unwrap())
+        .matches(|paste| paste.as_str() == "hello\r\nworld" && !paste.is_partial());
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Enter)));
+}
+
+#[tokio::test]
+async fn it_should_stream_long_bracketed_paste_as_partial_events() {
+    let content = "x".repeat(PASTE_CAPACITY + 10);
+    let mut input = format!("\x1b[200~{content}\x1b[201~");
+
+    let stream = events::stream(&mut input);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .map(|event| event.as_paste_event().unwrap())
+        .matches(|paste| paste.content.len() == PASTE_CAPACITY && paste.is_partial());
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .map(|event| event.as_paste_event().unwrap())
+        .matches(|paste| paste.as_str() == "x".repeat(10) && !paste.is_partial());
+
+    assert_that!(stream.next().await).is_none();
+}
+
+#[tokio::test]
+async fn it_should_stream_lone_escape_after_timeout() {
+    let mut input = IdleAfter(String::from("\x1b"));
+
+    let stream = events::stream_with_escape_timeout(&mut input, Sleep, Duration::from_millis(10));
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Escape)));
+}
+
+#[tokio::test]
+async fn it_should_stream_pending_input_at_end() {
+    let mut input = String::from("a\x1b[1;");
+
+    let stream = events::stream(&mut input);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Unknown(UnknownEvent {
+            truncated: true,
+            ..UnknownEvent::new(b"\x1b[1;")
+        }));
+
+    assert_that!(stream.next().await).is_none();
+}
+
+#[tokio::test]
+async fn it_should_stream_lone_escape_at_end() {
+    let mut input = String::from("\x1b");
+
+    let stream = events::stream(&mut input);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Escape)));
+
+    assert_that!(stream.next().await).is_none();
+}
+
+#[tokio::test]
+async fn it_should_stream_escape_sequence_before_timeout() {
+    let mut input = IdleAfter(String::from("\x1b[A\x1ba"));
+
+    let stream = events::stream_with_escape_timeout(&mut input, Sleep, Duration::from_millis(10));
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Up)));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(
+            KeyEvent::from(KeyCode::Char('a')).with_modifiers(KeyModifiers::ALT),
+        ));
+}
+
+#[tokio::test]
+async fn it_should_stream_multibyte_chars() {
+    let mut input = "é€".as_bytes().to_vec();
+
+    let stream = events::stream(&mut input);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('é'))));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('€'))));
+}
+
+#[tokio::test]
+async fn it_should_stream_invalid_utf8_as_error_and_keep_surrounding_input() {
+    let mut input = b"a\xff\xfeb".to_vec();
+
+    let stream = events::stream(&mut input);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_err_containing(crate::io::Error::Parse(ParseError::new(b"\xff")));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_err_containing(crate::io::Error::Parse(ParseError::new(b"\xfe")));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('b'))));
+}
+
+#[tokio::test]
+async fn it_should_stream_c1_controls_if_enabled() {
+    let mut input = b"\x9bA\x9b200~hi\x9b201~\x8fP".to_vec();
+
+    let config =
+        StreamConfig::new().with_parser_config(ParserConfig::default().with_c1_controls(true));
+
+    let stream = events::stream_with_config(&mut input, Never, config);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Up)));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .map(|event| event.as_paste_event().unwrap())
+        .matches(|paste| paste.as_str() == "hi" && !paste.is_partial());
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Fn(1))));
+}
+
+#[tokio::test]
+async fn it_should_stream_unknown_sequence_and_keep_surrounding_input() {
+    let mut input = String::from("a\x1b]52;c;aGVsbG8=\x1b\\b");
+
+    let stream = events::stream(&mut input);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Unknown(UnknownEvent::new(
+            b"\x1b]52;c;aGVsbG8=\x1b\\",
+        )));
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('b'))));
+}
+
+#[tokio::test]
+async fn it_should_stream_all_events_of_a_single_read() {
+    let mut input = Chunks::new(&[b"ab\x1b[Ac"]);
+
+    let events = events::stream(&mut input).collect::<Vec<_>>().await;
+
+    assert_that!(events).is_equal_to(vec![
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('a')))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('b')))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Up))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('c')))),
+    ]);
+
+    assert_that!(input.reads).is_equal_to(2);
+}
+
+#[tokio::test]
+async fn it_should_stream_sequence_split_across_reads() {
+    let mut input = Chunks::new(&[b"a\x1b[", b"1;5", b"2z\x1bO", b"Pb"]);
+
+    let events = events::stream(&mut input).collect::<Vec<_>>().await;
+
+    assert_that!(events).is_equal_to(vec![
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('a')))),
+        Ok(Event::Unknown(UnknownEvent::new(b"\x1b[1;52z"))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Fn(1)))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('b')))),
+    ]);
+}
+
+#[tokio::test]
+async fn it_should_stream_in_band_resize_split_across_reads() {
+    let mut input = Chunks::new(&[b"\x1b[48;24;8", b"0;480;640t"]);
+
+    let events = events::stream(&mut input).collect::<Vec<_>>().await;
+
+    assert_that!(events).is_equal_to(vec![Ok(Event::Screen(ScreenEvent::Resized {
+        width: 80,
+        height: 24,
+        pixel_width: 640,
+        pixel_height: 480,
+    }))]);
+}
+
+#[tokio::test]
+async fn it_should_stream_with_custom_sizes() {
+    let mut input = Chunks::new(&[b"\x1b]0;a window title longer than sixteen bytes\x07a"]);
+
+    let config = StreamConfig::new()
+        .with_buffer_size::<64>()
+        .with_queue_size::<2>();
+
+    let events = events::stream_with_config(&mut input, Never, config)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_that!(events).is_equal_to(vec![
+        Ok(Event::Unknown(UnknownEvent::new(
+            b"\x1b]0;a window title longer than sixteen bytes\x07",
+        ))),
+        Ok(Event::Key(KeyEvent::from(KeyCode::Char('a')))),
+    ]);
+}
+
+#[tokio::test]
+async fn it_should_stream_sequence_longer_than_buffer_as_truncated_unknown_event() {
+    let mut input = Chunks::new(&[b"\x1b]0;a title", b" longer than sixteen bytes\x07a"]);
+
+    let config = StreamConfig::new().with_buffer_size::<16>();
+
+    let stream = events::stream_with_config(&mut input, Never, config);
+    pin_mut!(stream);
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .map(|event| event.as_unknown_event().unwrap())
+        .matches(|event| event.is_truncated() && event.as_bytes() == b"\x1b]0;a title long");
+
+    assert_that!(stream.next().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyCode::Char('a').into()));
+}
+
+#[rstest]
+#[case::drop_oldest(
+    OverflowPolicy::DropOldest,
+    vec![Event::Overflow { dropped: 2 }, Event::Key(KeyCode::Char('c').into()), Event::Key(KeyCode::Char('d').into())],
+)]
+#[case::drop_newest(
+    OverflowPolicy::DropNewest,
+    vec![Event::Key(KeyCode::Char('a').into()), Event::Key(KeyCode::Char('b').into()), Event::Overflow { dropped: 2 }],
+)]
+#[case::backpressure(
+    OverflowPolicy::Backpressure,
+    vec![
+        Event::Key(KeyCode::Char('a').into()),
+        Event::Key(KeyCode::Char('b').into()),
+        Event::Key(KeyCode::Char('c').into()),
+        Event::Key(KeyCode::Char('d').into()),
+    ],
+)]
+#[tokio::test]
+async fn it_should_stream_overflow_according_to_policy(
+    #[case] policy: OverflowPolicy,
+    #[case] expected: Vec<Event>,
+) {
+    let mut input = Chunks::new(&[b"abcd"]);
+
+    let config = StreamConfig::new()
+        .with_queue_size::<2>()
+        .with_overflow_policy(policy);
+
+    let events = events::stream_with_config(&mut input, Never, config)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_that!(events).is_equal_to(expected);
+}
+
+#[tokio::test]
+async fn it_should_stream_long_bracketed_paste_with_backpressure() {
+    let content = "0123456789".repeat(20);
+    let mut input = format!("\x1b[200~{content}\x1b[201~a");
+
+    let config = StreamConfig::new()
+        .with_buffer_size::<256>()
+        .with_queue_size::<1>()
+        .with_overflow_policy(OverflowPolicy::Backpressure);
+
+    let events = events::stream_with_config(&mut input, Never, config)
+        .map(Result::unwrap)
+        .collect::<Vec<_>>()
+        .await;
+
+    let pasted = events
+        .iter()
+        .filter_map(Event::as_paste_event)
+        .map(|paste| paste.as_str())
+        .collect::<String>();
+
+    assert_that!(pasted).is_equal_to(content);
+    assert_that!(events.last()).is_equal_to(Some(&Event::Key(KeyCode::Char('a').into())));
+}
+
+#[tokio::test]
+async fn it_should_query_cursor_position_and_keep_other_events() {
+    let mut input = Chunks::new(&[b"a\x1b[12;4", b"0Rb"]);
+    let mut output = Vec::new();
+
+    let mut reader = AsyncEventReader::new(&mut input, Sleep);
+
+    assert_that!(
+        reader
+            .query_cursor_position(&mut output, Duration::from_millis(100))
+            .await
+    )
+    .is_ok_containing((40, 12));
+
+    assert_that!(output).is_equal_to(b"\x1b[6n".to_vec());
+
+    assert_that!(reader.next_event().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+
+    assert_that!(reader.next_event().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('b'))));
+}
+
+#[tokio::test]
+async fn it_should_time_out_query_without_reply() {
+    let mut input = IdleAfter(String::from("a"));
+    let mut output = Vec::new();
+
+    let mut reader = AsyncEventReader::new(&mut input, Sleep);
+
+    assert_that!(
+        reader
+            .query_keyboard_enhancement_flags(&mut output, Duration::from_millis(10))
+            .await
+    )
+    .is_err_containing(crate::io::Error::TimedOut);
+
+    assert_that!(reader.next_event().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+}
+
+#[rstest]
+#[case::drop_oldest(OverflowPolicy::DropOldest)]
+#[case::drop_newest(OverflowPolicy::DropNewest)]
+#[case::backpressure(OverflowPolicy::Backpressure)]
+#[tokio::test]
+async fn it_should_fail_query_early_once_too_many_events_are_kept(#[case] policy: OverflowPolicy) {
+    // A single event per read, so that the decoder queue never overflows on its own.
+    let mut input = Chunks::new(&[b"a", b"b", b"c", b"\x1b[12;40R"]);
+    let mut output = Vec::new();
+
+    let config = StreamConfig::new()
+        .with_queue_size::<2>()
+        .with_overflow_policy(policy);
+
+    let mut reader = AsyncEventReader::with_config(&mut input, Sleep, config);
+
+    let start = std::time::Instant::now();
+    assert_that!(
+        reader
+            .query_cursor_position(&mut output, Duration::from_secs(10))
+            .await
+    )
+    .is_err_containing(crate::io::Error::BufferFull);
+    assert_that!(start.elapsed()).is_less_than(Duration::from_secs(1));
+
+    let mut events = Vec::new();
+    for _ in 0..4 {
+        events.push(reader.next_event().await.unwrap().unwrap());
+    }
+
+    assert_that!(events).is_equal_to(vec![
+        Event::Key(KeyCode::Char('a').into()),
+        Event::Key(KeyCode::Char('b').into()),
+        Event::Key(KeyCode::Char('c').into()),
+        Event::Cursor(CursorEvent::Positioned { x: 40, y: 12 }),
+    ]);
+}
+
+#[tokio::test]
+async fn it_should_query_background_color() {
+    let mut input = Chunks::new(&[b"\x1b]11;rgb:0000/2b2b/3636\x1b\\"]);
+    let mut output = Vec::new();
+
+    let mut reader = AsyncEventReader::new(&mut input, Sleep);
+
+    assert_that!(
+        reader
+            .query_background_color(&mut output, Duration::from_millis(100))
+            .await
+    )
+    .is_ok_containing(Color::Rgb { r: 0, g: 43, b: 54 });
+
+    assert_that!(output).is_equal_to(b"\x1b]11;?\x1b\\".to_vec());
+}
+
+#[tokio::test]
+async fn it_should_query_mode_and_keep_other_mode_reports() {
+    let mut input = Chunks::new(&[b"\x1b[?2004;1$y\x1b[?2026;2$y"]);
+    let mut output = Vec::new();
+
+    let mut reader = AsyncEventReader::new(&mut input, Sleep);
+
+    assert_that!(
+        reader
+            .query_mode(
+                &mut output,
+                RequestMode::private(2026),
+                Duration::from_millis(100)
+            )
+            .await
+    )
+    .is_ok_containing(ModeState::Reset);
+
+    assert_that!(output).is_equal_to(b"\x1b[?2026$p".to_vec());
+
+    assert_that!(reader.next_event().await)
+        .is_some()
+        .is_ok()
+        .is_equal_to(Event::Mode(ModeReport {
+            mode: 2004,
+            private: true,
+            state: ModeState::Set,
+        }));
+}
+
+#[tokio::test]
+async fn it_should_query_size() {
+    let mut input = Chunks::new(&[b"\x1b[8;24;80t"]);
+    let mut output = Vec::new();
+
+    let mut reader = AsyncEventReader::new(&mut input, Sleep);
+
+    assert_that!(
+        reader
+            .query_size(&mut output, Duration::from_millis(100))
+            .await
+    )
+    .is_ok_containing(Size {
+        rows: 24,
+        columns: 80,
+    });
+
+    assert_that!(output).is_equal_to(b"\x1b[18t".to_vec());
+}
+
+#[tokio::test]
+async fn it_should_query_size_from_cursor_position_as_fallback() {
+    let mut input = Vt100::default();
+    let mut output = input.clone();
+
+    let mut reader = AsyncEventReader::new(&mut input, Sleep);
+
+    assert_that!(
+        reader
+            .query_size(&mut output, Duration::from_millis(20))
+            .await
+    )
+    .is_ok_containing(Size {
+        rows: 24,
+        columns: 80,
+    });
+}
+
+#[tokio::test]
+async fn it_should_probe_terminal_size() {
+    let mut input = Vt100::default();
+    let mut output = input.clone();
+
+    let mut reader = AsyncEventReader::new(&mut input, Sleep);
+    let mut terminal = Terminal::new(&mut output);
+
+    assert_that!(
+        terminal
+            .probe_size(&mut reader, Duration::from_millis(20))
+            .await
+            .copied()
+    )
+    .is_ok_containing(Size {
+        rows: 24,
+        columns: 80,
+    });
+
+    assert_that!(terminal.size()).is_equal_to(&Size {
+        rows: 24,
+        columns: 80,
+    });
+}
@@ -4,17 +4,77 @@ use core::fmt::Debug;
 
 pub mod blocking;
 
+/// Maximum number of offending bytes kept by a parse error.
+pub const PARSE_ERROR_CAPACITY: usize = 8;
+
 /// Error.
-#[derive(Debug, PartialEq, Eq, Hash, thiserror::Error)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, thiserror::Error)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Unknown error.
     #[error("unknown error")]
     Unknown,
+
+    /// Operation would block, e.g. no data is available yet.
+    #[error("operation would block")]
+    WouldBlock,
+
+    /// Operation was interrupted and may be retried.
+    #[error("operation interrupted")]
+    Interrupted,
+
+    /// End of input reached before the expected amount of data.
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+
+    /// Invalid data, e.g. a framing or parity error on a serial line.
+    #[error("invalid data")]
+    InvalidData,
+
+    /// Buffer is full, e.g. a transmit buffer that cannot accept more data.
+    #[error("buffer full")]
+    BufferFull,
+
+    /// Operation did not complete in time, e.g. a terminal query without reply.
+    #[error("operation timed out")]
+    TimedOut,
+
+    /// Input that can never be parsed into events.
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    /// Platform-specific error with its code.
+    #[error("error code {0}")]
+    Other(i32),
 }
 
 pub type Result<T, E = Error> = core::result::Result<T, E>;
 
+/// Error on input that can never be parsed, e.g. invalid UTF-8.
+///
+/// Only the first [`PARSE_ERROR_CAPACITY`] offending bytes are kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, thiserror::Error)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[error("invalid input {bytes:02x?}")]
+pub struct ParseError {
+    bytes: heapless::Vec<u8, PARSE_ERROR_CAPACITY>,
+}
+
+impl ParseError {
+    pub fn new(bytes: &[u8]) -> Self {
+        let len = bytes.len().min(PARSE_ERROR_CAPACITY);
+
+        Self {
+            bytes: heapless::Vec::from_slice(&bytes[..len]).unwrap_or_default(),
+        }
+    }
+
+    /// Get the offending bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
 /// Reader trait.
 pub trait Read {
     async fn read(&mut self, data: &mut [u8]) -> Result<usize>;
@@ -60,3 +120,42 @@ impl<WriterTy: Write> Write for &mut WriterTy {
         WriterTy::flush(*self).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    compile_error!("missing feature std to run tests");
+
+    use rstest::rstest;
+    use speculoos::prelude::*;
+
+    use super::*;
+
+    #[rstest]
+    #[case::unknown(Error::Unknown, "unknown error")]
+    #[case::would_block(Error::WouldBlock, "operation would block")]
+    #[case::interrupted(Error::Interrupted, "operation interrupted")]
+    #[case::unexpected_eof(Error::UnexpectedEof, "unexpected end of input")]
+    #[case::invalid_data(Error::InvalidData, "invalid data")]
+    #[case::buffer_full(Error::BufferFull, "buffer full")]
+    #[case::timed_out(Error::TimedOut, "operation timed out")]
+    #[case::parse(Error::Parse(ParseError::new(b"\xff\xfe")), "invalid input [ff, fe]")]
+    #[case::other(Error::Other(-5), "error code -5")]
+    fn it_should_display_error(#[case] error: Error, #[case] expected: &str) {
+        assert_that!(error.to_string().as_str()).is_equal_to(expected);
+    }
+
+    #[test]
+    fn it_should_convert_parse_error_into_error() {
+        let error = ParseError::new(b"\xff");
+
+        assert_that!(Error::from(error.clone())).is_equal_to(Error::Parse(error));
+    }
+
+    #[test]
+    fn it_should_keep_first_bytes_of_long_parse_error() {
+        let error = ParseError::new(&[0xff; PARSE_ERROR_CAPACITY + 4]);
+
+        assert_that!(error.as_bytes()).is_equal_to(&[0xff; PARSE_ERROR_CAPACITY][..]);
+    }
+}
@@ -59,3 +59,121 @@ impl Write for String {
         Ok(())
     }
 }
+
+#[cfg(feature = "std")]
+impl Write for Vec<u8> {
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        self.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer that emits 8-bit control sequence introducers.
+///
+/// Every `ESC [` written through it, e.g. by commands, is translated into its single byte C1 form,
+/// i.e. `0x9b`, for terminals that are configured to use 8-bit controls. A trailing escape byte is
+/// held back until the next write or flush.
+#[derive(Debug)]
+pub struct C1Writer<WriterTy> {
+    writer: WriterTy,
+    escape_pending: bool,
+}
+
+impl<WriterTy> C1Writer<WriterTy> {
+    pub fn new(writer: WriterTy) -> Self {
+        Self {
+            writer,
+            escape_pending: false,
+        }
+    }
+
+    /// Get back the underlying writer, dropping any pending escape byte.
+    pub fn into_inner(self) -> WriterTy {
+        self.writer
+    }
+}
+
+impl<WriterTy: Write> Write for C1Writer<WriterTy> {
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        const C1_CSI: &[u8] = &[0x9b];
+
+        let mut rest = data;
+
+        if self.escape_pending && !rest.is_empty() {
+            self.escape_pending = false;
+
+            if rest[0] == b'[' {
+                self.writer.write_all(C1_CSI)?;
+                rest = &rest[1..];
+            } else {
+                self.writer.write_all(b"\x1b")?;
+            }
+        }
+
+        while let Some(pos) = rest.iter().position(|&b| b == b'\x1b') {
+            self.writer.write_all(&rest[..pos])?;
+
+            match rest.get(pos + 1) {
+                Some(b'[') => {
+                    self.writer.write_all(C1_CSI)?;
+                    rest = &rest[pos + 2..];
+                }
+                Some(_) => {
+                    self.writer.write_all(b"\x1b")?;
+                    rest = &rest[pos + 1..];
+                }
+                None => {
+                    self.escape_pending = true;
+                    rest = &[];
+                }
+            }
+        }
+
+        self.writer.write_all(rest)?;
+
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if self.escape_pending {
+            self.escape_pending = false;
+            self.writer.write_all(b"\x1b")?;
+        }
+
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    compile_error!("missing feature std to run tests");
+
+    use speculoos::prelude::*;
+
+    use crate::Executable;
+    use crate::terminal::EnableBracketedPaste;
+
+    use super::*;
+
+    #[test]
+    fn it_should_write_c1_csi() {
+        let mut writer = C1Writer::new(Vec::new());
+        writer.execute(EnableBracketedPaste).unwrap();
+        assert_that!(writer.into_inner()).is_equal_to(b"\x9b?2004h".to_vec());
+    }
+
+    #[test]
+    fn it_should_write_c1_csi_across_writes() {
+        let mut writer = C1Writer::new(Vec::new());
+        writer.write_all(b"a\x1b").unwrap();
+        writer.write_all(b"[A\x1bOP").unwrap();
+        writer.write_all(b"\x1b").unwrap();
+        writer.flush().unwrap();
+        assert_that!(writer.into_inner()).is_equal_to(b"a\x9bA\x1bOP\x1b".to_vec());
+    }
+}
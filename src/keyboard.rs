@@ -0,0 +1,131 @@
+//! Keyboard.
+//!
+//! Commands for the progressive enhancement of the [kitty keyboard protocol].
+//!
+//! [kitty keyboard protocol]: https://sw.kovidgoyal.net/kitty/keyboard-protocol/
+
+use core::fmt;
+
+use crate::events::KeyboardEnhancementFlags;
+use crate::terminal::TerminalCapabilities;
+use crate::{Command, csi};
+
+/// Push the given flags onto the terminal stack of keyboard enhancement flags.
+///
+/// Nothing is written for terminals that do not support the protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PushKeyboardEnhancementFlags(pub KeyboardEnhancementFlags);
+
+impl Command for PushKeyboardEnhancementFlags {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!(">{}u"), self.0.bits())
+    }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if capabilities.kitty_keyboard {
+            self.write(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Pop the last flags pushed onto the terminal stack of keyboard enhancement flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PopKeyboardEnhancementFlags;
+
+impl Command for PopKeyboardEnhancementFlags {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("<1u"))
+    }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if capabilities.kitty_keyboard {
+            self.write(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Query the current keyboard enhancement flags.
+///
+/// The terminal replies with an [`Event::KeyboardEnhancementFlags`] if it supports the protocol.
+///
+/// [`Event::KeyboardEnhancementFlags`]: crate::events::Event::KeyboardEnhancementFlags
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QueryKeyboardEnhancementFlags;
+
+impl Command for QueryKeyboardEnhancementFlags {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?u"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    compile_error!("missing feature std to run tests");
+
+    use speculoos::prelude::*;
+
+    use crate::Executable;
+    use crate::terminal::Terminal;
+
+    use super::*;
+
+    #[test]
+    fn it_should_write_push_keyboard_enhancement_flags_action() {
+        let action = PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+        );
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!(">3u"));
+    }
+
+    #[test]
+    fn it_should_write_pop_keyboard_enhancement_flags_action() {
+        let mut buffer = String::default();
+
+        let result = buffer.execute(PopKeyboardEnhancementFlags);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("<1u"));
+    }
+
+    #[test]
+    fn it_should_write_query_keyboard_enhancement_flags_action() {
+        let mut buffer = String::default();
+
+        let result = buffer.execute(QueryKeyboardEnhancementFlags);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("?u"));
+    }
+
+    #[test]
+    fn it_should_skip_keyboard_enhancement_flags_without_support() {
+        let mut buffer = String::default();
+        let mut terminal =
+            Terminal::new(&mut buffer).with_capabilities(TerminalCapabilities::XTERM);
+
+        let result = terminal
+            .queue(PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES,
+            ))
+            .and_then(|terminal| terminal.execute(PopKeyboardEnhancementFlags));
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_empty();
+    }
+}
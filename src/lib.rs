@@ -4,17 +4,34 @@
 
 use core::fmt;
 
+use crate::terminal::TerminalCapabilities;
+
 mod macros;
 
 pub mod cursor;
 pub mod events;
 pub mod io;
+pub mod keyboard;
+pub mod mouse;
 pub mod style;
 pub mod terminal;
+pub mod time;
 
 /// Command trait.
 pub trait Command {
     fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result;
+
+    /// Write the command for a terminal with the given capabilities, so that it degrades or does
+    /// nothing rather than emitting sequences the terminal does not understand.
+    ///
+    /// By default, the command is written as is.
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        _capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        self.write(writer)
+    }
 }
 
 pub trait Queuable {
@@ -44,32 +61,47 @@ fn command_write_ansi<WriterTy: io::blocking::Write, CommandTy: Command>(
     writer: &mut WriterTy,
     command: CommandTy,
 ) -> io::Result<()> {
-    struct Adapter<Ty> {
-        inner: Ty,
-        result: io::Result<()>,
-    }
+    write_adapted(writer, |adapter| command.write(adapter))
+}
 
-    impl<Ty: io::blocking::Write> fmt::Write for Adapter<Ty> {
-        fn write_str(&mut self, s: &str) -> fmt::Result {
-            match self.inner.write_all(s.as_bytes()) {
-                Ok(_) => Ok(()),
-                Err(err) => {
-                    self.result = Err(err);
-                    Err(fmt::Error)
-                }
+/// Write the given command for a terminal with the given capabilities, see
+/// [`Command::write_with`].
+pub(crate) fn command_write_ansi_with<WriterTy: io::blocking::Write, CommandTy: Command>(
+    writer: &mut WriterTy,
+    command: CommandTy,
+    capabilities: &TerminalCapabilities,
+) -> io::Result<()> {
+    write_adapted(writer, |adapter| command.write_with(adapter, capabilities))
+}
+
+struct Adapter<Ty> {
+    inner: Ty,
+    result: io::Result<()>,
+}
+
+impl<Ty: io::blocking::Write> fmt::Write for Adapter<Ty> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.inner.write_all(s.as_bytes()) {
+            Ok(_) => Ok(()),
+            Err(err) => {
+                self.result = Err(err);
+                Err(fmt::Error)
             }
         }
     }
+}
 
+fn write_adapted<WriterTy: io::blocking::Write>(
+    writer: &mut WriterTy,
+    write: impl FnOnce(&mut Adapter<&mut WriterTy>) -> fmt::Result,
+) -> io::Result<()> {
     let mut adapter = Adapter {
         inner: writer,
         result: Ok(()),
     };
 
-    command
-        .write(&mut adapter)
-        .map_err(|_| match adapter.result {
-            Ok(()) => panic!("command write incorrectly errored"),
-            Err(err) => err,
-        })
+    write(&mut adapter).map_err(|_| match adapter.result {
+        Ok(()) => panic!("command write incorrectly errored"),
+        Err(err) => err,
+    })
 }
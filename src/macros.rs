@@ -11,6 +11,14 @@ macro_rules! csi {
     }
 }
 
+#[macro_export]
+#[doc(hidden)]
+macro_rules! osc {
+    ($($item:expr),*) => {
+        concat!("\x1b]", $($item),*, "\x1b\\")
+    }
+}
+
 #[macro_export]
 macro_rules! print {
     ($output:expr, $fmt:expr $(, $($args:expr),*)? $(,)?) => {{
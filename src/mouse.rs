@@ -0,0 +1,134 @@
+//! Mouse.
+
+use core::fmt;
+
+use crate::terminal::TerminalCapabilities;
+use crate::{Command, csi};
+
+/// Enable mouse capture.
+///
+/// It enables button, drag and motion tracking and asks the terminal for SGR (1006) encoded
+/// reports, falling back to urxvt (1015) and then to legacy X10 ones. SGR reports are not asked
+/// for from terminals that lack them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EnableMouseCapture;
+
+impl Command for EnableMouseCapture {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?1000h"))?;
+        write!(writer, csi!("?1002h"))?;
+        write!(writer, csi!("?1003h"))?;
+        write!(writer, csi!("?1015h"))?;
+        write!(writer, csi!("?1006h"))
+    }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        write!(writer, csi!("?1000h"))?;
+        write!(writer, csi!("?1002h"))?;
+        write!(writer, csi!("?1003h"))?;
+        write!(writer, csi!("?1015h"))?;
+        if capabilities.sgr_mouse {
+            write!(writer, csi!("?1006h"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Disable mouse capture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DisableMouseCapture;
+
+impl Command for DisableMouseCapture {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?1006l"))?;
+        write!(writer, csi!("?1015l"))?;
+        write!(writer, csi!("?1003l"))?;
+        write!(writer, csi!("?1002l"))?;
+        write!(writer, csi!("?1000l"))
+    }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if capabilities.sgr_mouse {
+            write!(writer, csi!("?1006l"))?;
+        }
+        write!(writer, csi!("?1015l"))?;
+        write!(writer, csi!("?1003l"))?;
+        write!(writer, csi!("?1002l"))?;
+        write!(writer, csi!("?1000l"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    compile_error!("missing feature std to run tests");
+
+    use speculoos::prelude::*;
+
+    use crate::Executable;
+    use crate::terminal::Terminal;
+
+    use super::*;
+
+    #[test]
+    fn it_should_write_enable_mouse_capture_action() {
+        let mut buffer = String::default();
+
+        let result = buffer.execute(EnableMouseCapture);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(concat!(
+            csi!("?1000h"),
+            csi!("?1002h"),
+            csi!("?1003h"),
+            csi!("?1015h"),
+            csi!("?1006h")
+        ));
+    }
+
+    #[test]
+    fn it_should_write_disable_mouse_capture_action() {
+        let mut buffer = String::default();
+
+        let result = buffer.execute(DisableMouseCapture);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(concat!(
+            csi!("?1006l"),
+            csi!("?1015l"),
+            csi!("?1003l"),
+            csi!("?1002l"),
+            csi!("?1000l")
+        ));
+    }
+
+    #[test]
+    fn it_should_not_ask_for_sgr_mouse_reports_without_support() {
+        let mut buffer = String::default();
+        let mut terminal =
+            Terminal::new(&mut buffer).with_capabilities(TerminalCapabilities::LINUX);
+
+        let result = terminal
+            .queue(EnableMouseCapture)
+            .and_then(|terminal| terminal.execute(DisableMouseCapture));
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(concat!(
+            csi!("?1000h"),
+            csi!("?1002h"),
+            csi!("?1003h"),
+            csi!("?1015h"),
+            csi!("?1015l"),
+            csi!("?1003l"),
+            csi!("?1002l"),
+            csi!("?1000l")
+        ));
+    }
+}
@@ -5,13 +5,14 @@ use core::{fmt, marker::PhantomData};
 use crate::{
     Command, csi,
     style::colors::{Background, Foreground, Underline},
+    terminal::TerminalCapabilities,
 };
 
 pub mod attributes;
 pub mod colors;
 
 pub use attributes::{Attribute, AttributeSet};
-pub use colors::Color;
+pub use colors::{Color, ColorDepth, ColorScheme};
 
 pub struct SetBackgroundColor(pub Color);
 
@@ -19,6 +20,17 @@ impl Command for SetBackgroundColor {
     fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
         write!(writer, csi!("{}m"), Background(self.0))
     }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if let Some(sgr) = Background(self.0).with_depth(capabilities.color_depth()) {
+            write!(writer, csi!("{}m"), sgr)?;
+        }
+        Ok(())
+    }
 }
 
 pub struct SetForegroundColor(pub Color);
@@ -27,6 +39,17 @@ impl Command for SetForegroundColor {
     fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
         write!(writer, csi!("{}m"), Foreground(self.0))
     }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if let Some(sgr) = Foreground(self.0).with_depth(capabilities.color_depth()) {
+            write!(writer, csi!("{}m"), sgr)?;
+        }
+        Ok(())
+    }
 }
 
 pub struct SetUnderlineColor(pub Color);
@@ -35,6 +58,17 @@ impl Command for SetUnderlineColor {
     fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
         write!(writer, csi!("{}m"), Underline(self.0))
     }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if let Some(sgr) = Underline(self.0).with_depth(capabilities.color_depth()) {
+            write!(writer, csi!("{}m"), sgr)?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
@@ -67,6 +101,29 @@ impl Command for SetColors {
 
         Ok(())
     }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        let depth = capabilities.color_depth();
+        let fg = self.0.fg.and_then(|fg| Foreground(fg).with_depth(depth));
+        let bg = self.0.bg.and_then(|bg| Background(bg).with_depth(depth));
+
+        match (fg, bg) {
+            (Some(fg), Some(bg)) => write!(writer, csi!("{};{}m"), fg, bg)?,
+            (Some(fg), None) => write!(writer, csi!("{}m"), fg)?,
+            (None, Some(bg)) => write!(writer, csi!("{}m"), bg)?,
+            (None, None) => {}
+        }
+
+        if let Some(ul) = self.0.ul.and_then(|ul| Underline(ul).with_depth(depth)) {
+            write!(writer, csi!("{}m"), ul)?;
+        }
+
+        Ok(())
+    }
 }
 
 pub struct ResetColor;
@@ -124,6 +181,16 @@ impl Command for SetStyle {
         SetAttributes(self.0.attributes).write(writer)?;
         Ok(())
     }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        SetColors(self.0.colors).write_with(writer, capabilities)?;
+        SetAttributes(self.0.attributes).write(writer)?;
+        Ok(())
+    }
 }
 
 pub struct Print<ContentTy: fmt::Display>(pub ContentTy);
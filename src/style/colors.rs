@@ -1,8 +1,12 @@
 //! Colors.
 
 use core::fmt;
+use core::ops::RangeInclusive;
+
+use crate::terminal::TerminalCapabilities;
 
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Color {
     #[default]
     Reset,
@@ -34,6 +38,194 @@ pub enum Color {
     Ansi(u8),
 }
 
+/// Default xterm values of the 16 named colors, indexed by their ANSI value.
+const ANSI_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+impl Color {
+    /// Get the red, green and blue components of the color, assuming the default xterm palette for
+    /// named and ANSI colors.
+    ///
+    /// The reset color has no value on its own, so that it yields `None`.
+    pub fn to_rgb(&self) -> Option<(u8, u8, u8)> {
+        if let Color::Rgb { r, g, b } = *self {
+            return Some((r, g, b));
+        }
+
+        let index = self.ansi_index()?;
+
+        Some(match index {
+            0..=15 => ANSI_PALETTE[index as usize],
+            16..=231 => {
+                let level = |value: u8| if value == 0 { 0 } else { 55 + 40 * value };
+                let index = index - 16;
+                (level(index / 36), level(index / 6 % 6), level(index % 6))
+            }
+            232..=255 => {
+                let level = 8 + 10 * (index - 232);
+                (level, level, level)
+            }
+        })
+    }
+
+    /// Approximate the color with the nearest one available with the given depth.
+    ///
+    /// RGB colors are approximated by perceptual distance, using the default xterm palette. A
+    /// monochrome terminal has no color at all, so that any color yields the reset color.
+    pub fn downsample(self, depth: ColorDepth) -> Color {
+        match (depth, self) {
+            (_, Color::Reset) | (ColorDepth::TrueColor, _) => self,
+            (ColorDepth::Monochrome, _) => Color::Reset,
+            (ColorDepth::Palette, Color::Rgb { r, g, b }) => {
+                // The first 16 colors are left out as they are often customized.
+                Color::Ansi(nearest((r, g, b), 16..=255))
+            }
+            (ColorDepth::Palette, _) => self,
+            (ColorDepth::Ansi, _) => match self.ansi_index() {
+                Some(index @ 0..=15) => Color::Ansi(index),
+                _ => match self.to_rgb() {
+                    Some(rgb) => Color::Ansi(nearest(rgb, 0..=15)),
+                    None => Color::Reset,
+                },
+            },
+        }
+    }
+
+    /// Get the index of the color in the xterm palette, if any.
+    fn ansi_index(&self) -> Option<u8> {
+        match *self {
+            Color::Reset | Color::Rgb { .. } => None,
+
+            Color::Black => Some(0),
+            Color::DarkRed => Some(1),
+            Color::DarkGreen => Some(2),
+            Color::DarkYellow => Some(3),
+            Color::DarkBlue => Some(4),
+            Color::DarkMagenta => Some(5),
+            Color::DarkCyan => Some(6),
+            Color::Grey => Some(7),
+
+            Color::DarkGrey => Some(8),
+            Color::Red => Some(9),
+            Color::Green => Some(10),
+            Color::Yellow => Some(11),
+            Color::Blue => Some(12),
+            Color::Magenta => Some(13),
+            Color::Cyan => Some(14),
+            Color::White => Some(15),
+
+            Color::Ansi(value) => Some(value),
+        }
+    }
+
+    /// Tell whether the color is perceived as light, if it has a value on its own.
+    fn is_light(&self) -> Option<bool> {
+        let (r, g, b) = self.to_rgb()?;
+        let brightness = (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000;
+        Some(brightness >= 128)
+    }
+}
+
+/// Get the index of the palette color nearest to the given one.
+fn nearest(rgb: (u8, u8, u8), candidates: RangeInclusive<u8>) -> u8 {
+    candidates
+        .min_by_key(|&index| {
+            let candidate = Color::Ansi(index).to_rgb().unwrap_or_default();
+            distance(rgb, candidate)
+        })
+        .unwrap_or_default()
+}
+
+/// Get the perceptual distance between two colors, as a weighted euclidean distance in the RGB
+/// space, a.k.a. "redmean".
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let mean = (r1 as u32 + r2 as u32) / 2;
+    let (dr, dg, db) = (
+        r1.abs_diff(r2) as u32,
+        g1.abs_diff(g2) as u32,
+        b1.abs_diff(b2) as u32,
+    );
+
+    (((512 + mean) * dr * dr) >> 8) + 4 * dg * dg + (((767 - mean) * db * db) >> 8)
+}
+
+/// Number of colors a terminal is able to display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ColorDepth {
+    /// No colors at all. Light foreground and background colors are approximated with the bold
+    /// and reverse attributes respectively, which other colors turn off.
+    Monochrome,
+    /// The 16 named colors, written with the classic `30–37` and `90–97` codes.
+    Ansi,
+    /// The 256 colors of the xterm palette.
+    Palette,
+    /// 24-bit RGB colors.
+    #[default]
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Get the color depth of a terminal with the given capabilities.
+    pub fn from_capabilities(capabilities: &TerminalCapabilities) -> Self {
+        if capabilities.truecolor {
+            ColorDepth::TrueColor
+        } else if capabilities.colors_256 {
+            ColorDepth::Palette
+        } else if capabilities.ansi_colors {
+            ColorDepth::Ansi
+        } else {
+            ColorDepth::Monochrome
+        }
+    }
+}
+
+/// Color scheme of a terminal, i.e. whether text is written on a dark or a light background.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ColorScheme {
+    Dark,
+    Light,
+}
+
+impl ColorScheme {
+    /// Classify a background color by its perceived brightness, e.g. as reported by
+    /// [`QueryBackgroundColor`](crate::terminal::QueryBackgroundColor).
+    ///
+    /// The reset color is unknown, so that it yields `None`.
+    pub fn from_background(color: Color) -> Option<Self> {
+        if color.is_light()? {
+            Some(ColorScheme::Light)
+        } else {
+            Some(ColorScheme::Dark)
+        }
+    }
+
+    pub fn is_dark(&self) -> bool {
+        *self == ColorScheme::Dark
+    }
+
+    pub fn is_light(&self) -> bool {
+        *self == ColorScheme::Light
+    }
+}
+
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -65,6 +257,13 @@ impl fmt::Display for Color {
 
 pub struct Background(pub Color);
 
+impl Background {
+    /// Approximate the color with the given depth, if the terminal is able to display it at all.
+    pub fn with_depth(&self, depth: ColorDepth) -> Option<ColorSgr> {
+        ColorSgr::new(self.0, depth, 10)
+    }
+}
+
 impl fmt::Display for Background {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.0 == Color::Reset {
@@ -77,6 +276,13 @@ impl fmt::Display for Background {
 
 pub struct Foreground(pub Color);
 
+impl Foreground {
+    /// Approximate the color with the given depth, if the terminal is able to display it at all.
+    pub fn with_depth(&self, depth: ColorDepth) -> Option<ColorSgr> {
+        ColorSgr::new(self.0, depth, 0)
+    }
+}
+
 impl fmt::Display for Foreground {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.0 == Color::Reset {
@@ -89,6 +295,16 @@ impl fmt::Display for Foreground {
 
 pub struct Underline(pub Color);
 
+impl Underline {
+    /// Approximate the color with the given depth, if the terminal is able to display it at all.
+    ///
+    /// Underline colors are an extension of true color terminals, so that there is nothing to
+    /// write below true color.
+    pub fn with_depth(&self, depth: ColorDepth) -> Option<ColorSgr> {
+        ColorSgr::new(self.0, depth, 20)
+    }
+}
+
 impl fmt::Display for Underline {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if self.0 == Color::Reset {
@@ -98,3 +314,167 @@ impl fmt::Display for Underline {
         }
     }
 }
+
+/// SGR parameters of a color approximated with a given depth, see e.g. [`Foreground::with_depth`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ColorSgr {
+    code: u8,
+    color: Option<Color>,
+}
+
+impl ColorSgr {
+    /// Approximate a color, where the offset of the codes is `0` for foreground, `10` for
+    /// background and `20` for underline colors.
+    ///
+    /// On monochrome terminals, light foreground and background colors are approximated with the
+    /// bold and reverse attributes respectively, which other colors turn off again so that none is
+    /// left on after e.g. a reset. Foreground colors thus clear the bold attribute, and background
+    /// colors the reverse one.
+    fn new(color: Color, depth: ColorDepth, offset: u8) -> Option<Self> {
+        let code = |code| Some(ColorSgr { code, color: None });
+
+        match (depth, offset) {
+            (ColorDepth::TrueColor, _) => {}
+            (_, 20) => return None,
+            (ColorDepth::Monochrome, 0) if color.is_light() == Some(true) => return code(1),
+            (ColorDepth::Monochrome, 0) => return code(22),
+            (ColorDepth::Monochrome, 10) if color.is_light() == Some(true) => return code(7),
+            (ColorDepth::Monochrome, _) => return code(27),
+            (ColorDepth::Ansi | ColorDepth::Palette, _) => {}
+        }
+
+        match color.downsample(depth) {
+            Color::Reset => code(39 + offset),
+            Color::Ansi(index @ 0..=7) if depth == ColorDepth::Ansi => code(30 + offset + index),
+            Color::Ansi(index @ 8..=15) if depth == ColorDepth::Ansi => {
+                code(90 + offset + index - 8)
+            }
+            color => Some(ColorSgr {
+                code: 38 + offset,
+                color: Some(color),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ColorSgr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.color {
+            Some(color) => write!(f, "{};{}", self.code, color),
+            None => write!(f, "{}", self.code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    compile_error!("missing feature std to run tests");
+
+    use rstest::rstest;
+    use speculoos::prelude::*;
+
+    use super::*;
+
+    #[rstest]
+    #[case::named(Color::DarkRed, Some((205, 0, 0)))]
+    #[case::ansi_named(Color::Ansi(15), Some((255, 255, 255)))]
+    #[case::ansi_cube(Color::Ansi(67), Some((95, 135, 175)))]
+    #[case::ansi_greyscale(Color::Ansi(244), Some((128, 128, 128)))]
+    #[case::rgb(Color::Rgb { r: 1, g: 2, b: 3 }, Some((1, 2, 3)))]
+    #[case::reset(Color::Reset, None)]
+    fn it_should_convert_color_to_rgb(
+        #[case] color: Color,
+        #[case] expected: Option<(u8, u8, u8)>,
+    ) {
+        assert_that!(color.to_rgb()).is_equal_to(expected);
+    }
+
+    #[rstest]
+    #[case::black(Color::Black, Some(ColorScheme::Dark))]
+    #[case::solarized_dark(Color::Rgb { r: 0, g: 43, b: 54 }, Some(ColorScheme::Dark))]
+    #[case::solarized_light(Color::Rgb { r: 253, g: 246, b: 227 }, Some(ColorScheme::Light))]
+    #[case::white(Color::White, Some(ColorScheme::Light))]
+    #[case::reset(Color::Reset, None)]
+    fn it_should_classify_background_color(
+        #[case] color: Color,
+        #[case] expected: Option<ColorScheme>,
+    ) {
+        assert_that!(ColorScheme::from_background(color)).is_equal_to(expected);
+    }
+
+    #[rstest]
+    #[case::true_color(Color::Rgb { r: 1, g: 2, b: 3 }, ColorDepth::TrueColor, Color::Rgb { r: 1, g: 2, b: 3 })]
+    #[case::palette_rgb(Color::Rgb { r: 255, g: 0, b: 0 }, ColorDepth::Palette, Color::Ansi(196))]
+    #[case::palette_greyscale(Color::Rgb { r: 130, g: 128, b: 127 }, ColorDepth::Palette, Color::Ansi(244))]
+    #[case::palette_named(Color::DarkRed, ColorDepth::Palette, Color::DarkRed)]
+    #[case::ansi_rgb(Color::Rgb { r: 250, g: 5, b: 5 }, ColorDepth::Ansi, Color::Ansi(9))]
+    #[case::ansi_palette(Color::Ansi(196), ColorDepth::Ansi, Color::Ansi(9))]
+    #[case::ansi_named(Color::DarkRed, ColorDepth::Ansi, Color::Ansi(1))]
+    #[case::monochrome(Color::White, ColorDepth::Monochrome, Color::Reset)]
+    #[case::reset(Color::Reset, ColorDepth::Ansi, Color::Reset)]
+    fn it_should_downsample_color(
+        #[case] color: Color,
+        #[case] depth: ColorDepth,
+        #[case] expected: Color,
+    ) {
+        assert_that!(color.downsample(depth)).is_equal_to(expected);
+    }
+
+    #[rstest]
+    #[case::true_color(Foreground(Color::Rgb { r: 1, g: 2, b: 3 }), "38;2;1;2;3")]
+    #[case::named(Foreground(Color::DarkRed), "38;5;1")]
+    #[case::reset(Foreground(Color::Reset), "39")]
+    fn it_should_format_foreground(#[case] foreground: Foreground, #[case] expected: &str) {
+        assert_that!(foreground.to_string().as_str()).is_equal_to(expected);
+    }
+
+    #[rstest]
+    #[case::true_color(Foreground(Color::Rgb { r: 1, g: 2, b: 3 }), ColorDepth::TrueColor, Some("38;2;1;2;3"))]
+    #[case::palette(Foreground(Color::Rgb { r: 255, g: 0, b: 0 }), ColorDepth::Palette, Some("38;5;196"))]
+    #[case::ansi(Foreground(Color::DarkRed), ColorDepth::Ansi, Some("31"))]
+    #[case::ansi_bright(Foreground(Color::Red), ColorDepth::Ansi, Some("91"))]
+    #[case::ansi_reset(Foreground(Color::Reset), ColorDepth::Ansi, Some("39"))]
+    #[case::monochrome_light(Foreground(Color::White), ColorDepth::Monochrome, Some("1"))]
+    #[case::monochrome_dark(Foreground(Color::Black), ColorDepth::Monochrome, Some("22"))]
+    #[case::monochrome_reset(Foreground(Color::Reset), ColorDepth::Monochrome, Some("22"))]
+    fn it_should_format_foreground_with_depth(
+        #[case] foreground: Foreground,
+        #[case] depth: ColorDepth,
+        #[case] expected: Option<&str>,
+    ) {
+        let sgr = foreground.with_depth(depth).map(|sgr| sgr.to_string());
+        assert_that!(sgr.as_deref()).is_equal_to(expected);
+    }
+
+    #[rstest]
+    #[case::ansi(Background(Color::DarkBlue), ColorDepth::Ansi, Some("44"))]
+    #[case::ansi_rgb(Background(Color::Rgb { r: 255, g: 255, b: 255 }), ColorDepth::Ansi, Some("107"))]
+    #[case::monochrome_light(Background(Color::White), ColorDepth::Monochrome, Some("7"))]
+    #[case::monochrome_dark(Background(Color::Black), ColorDepth::Monochrome, Some("27"))]
+    #[case::monochrome_reset(Background(Color::Reset), ColorDepth::Monochrome, Some("27"))]
+    fn it_should_format_background_with_depth(
+        #[case] background: Background,
+        #[case] depth: ColorDepth,
+        #[case] expected: Option<&str>,
+    ) {
+        let sgr = background.with_depth(depth).map(|sgr| sgr.to_string());
+        assert_that!(sgr.as_deref()).is_equal_to(expected);
+    }
+
+    #[rstest]
+    #[case::true_color(Underline(Color::DarkRed), ColorDepth::TrueColor, Some("58;5;1"))]
+    #[case::true_color_reset(Underline(Color::Reset), ColorDepth::TrueColor, Some("59"))]
+    #[case::palette(Underline(Color::DarkRed), ColorDepth::Palette, None)]
+    #[case::ansi(Underline(Color::DarkRed), ColorDepth::Ansi, None)]
+    #[case::monochrome(Underline(Color::White), ColorDepth::Monochrome, None)]
+    fn it_should_format_underline_with_depth(
+        #[case] underline: Underline,
+        #[case] depth: ColorDepth,
+        #[case] expected: Option<&str>,
+    ) {
+        let sgr = underline.with_depth(depth).map(|sgr| sgr.to_string());
+        assert_that!(sgr.as_deref()).is_equal_to(expected);
+    }
+}
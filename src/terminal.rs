@@ -5,11 +5,19 @@
 
 use core::fmt;
 
+use core::time::Duration;
+
+use crate::events::AsyncEventReader;
 use crate::io;
-use crate::{Command, csi};
+use crate::time::Delay;
+use crate::{Command, csi, osc};
+
+pub mod capabilities;
+
+pub use capabilities::TerminalCapabilities;
 
 /// Terminal config.
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Size {
     pub rows: u16,
@@ -22,6 +30,7 @@ pub struct Terminal<'a, WriterTy> {
     writer: &'a mut WriterTy,
     size: Size,
     cursor: (u16, u16),
+    capabilities: TerminalCapabilities,
 }
 
 impl<'a, WriterTy> Terminal<'a, WriterTy>
@@ -37,6 +46,7 @@ where
                 columns: 80,
             },
             cursor: (0, 0),
+            capabilities: TerminalCapabilities::KITTY,
         }
     }
 
@@ -46,19 +56,103 @@ where
         self
     }
 
+    /// Create a new terminal with the given capabilities, e.g. a built-in profile from
+    /// [`TerminalCapabilities::from_term`].
+    ///
+    /// Otherwise, the terminal is assumed to support everything.
+    pub fn with_capabilities(mut self, capabilities: TerminalCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
     /// Get inner writer.
     pub fn writer(&'a mut self) -> &'a mut WriterTy {
         self.writer
     }
+
+    /// Get the size.
+    pub fn size(&self) -> &Size {
+        &self.size
+    }
+
+    /// Get the capabilities.
+    pub fn capabilities(&self) -> &TerminalCapabilities {
+        &self.capabilities
+    }
+
+    /// Queue the given command, written according to the capabilities of the terminal, see
+    /// [`Command::write_with`].
+    pub fn queue(&mut self, command: impl Command) -> io::Result<&mut Self> {
+        crate::command_write_ansi_with(self.writer, command, &self.capabilities)?;
+        Ok(self)
+    }
+
+    /// Execute the given command, written according to the capabilities of the terminal, see
+    /// [`Command::write_with`].
+    pub fn execute(&mut self, command: impl Command) -> io::Result<&mut Self> {
+        self.queue(command)?;
+        self.writer.flush()?;
+        Ok(self)
+    }
+
+    /// Probe the actual capabilities of the terminal on top of the given baseline and keep them,
+    /// see [`capabilities::probe`].
+    pub async fn probe_capabilities<
+        ReaderTy,
+        DelayTy,
+        const BUFFER_SIZE: usize,
+        const QUEUE_SIZE: usize,
+    >(
+        &mut self,
+        events: &mut AsyncEventReader<'_, ReaderTy, DelayTy, BUFFER_SIZE, QUEUE_SIZE>,
+        baseline: TerminalCapabilities,
+        timeout: Duration,
+    ) -> io::Result<&TerminalCapabilities>
+    where
+        ReaderTy: io::Read,
+        DelayTy: Delay,
+    {
+        self.capabilities = capabilities::probe(events, self.writer, baseline, timeout).await?;
+        Ok(&self.capabilities)
+    }
+
+    /// Probe the actual size of the terminal and keep it, see
+    /// [`AsyncEventReader::query_size`].
+    pub async fn probe_size<ReaderTy, DelayTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>(
+        &mut self,
+        events: &mut AsyncEventReader<'_, ReaderTy, DelayTy, BUFFER_SIZE, QUEUE_SIZE>,
+        timeout: Duration,
+    ) -> io::Result<&Size>
+    where
+        ReaderTy: io::Read,
+        DelayTy: Delay,
+    {
+        self.size = events.query_size(self.writer, timeout).await?;
+        Ok(&self.size)
+    }
 }
 
 /// Error.
-#[derive(Clone, Debug, thiserror::Error)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, thiserror::Error)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Unknown.
     #[error("unkown error")]
     Unknown,
+
+    /// I/O error.
+    #[error("i/o error: {0}")]
+    Io(#[from] io::Error),
+
+    /// Command could not be formatted.
+    #[error("formatting error")]
+    Format,
+}
+
+impl From<fmt::Error> for Error {
+    fn from(_: fmt::Error) -> Self {
+        Error::Format
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -89,6 +183,18 @@ impl Command for Clear {
             ClearType::Line => write!(writer, csi!("2K")),
         }
     }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if self.0 == ClearType::History && !capabilities.clear_history {
+            return Ok(());
+        }
+
+        self.write(writer)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -150,6 +256,227 @@ impl Command for EnableLineWrapping {
     }
 }
 
+/// Enable bracketed paste, so that pasted text is reported as a single
+/// [`Event::Paste`](crate::events::Event::Paste) instead of individual keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EnableBracketedPaste;
+
+impl Command for EnableBracketedPaste {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?2004h"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DisableBracketedPaste;
+
+impl Command for DisableBracketedPaste {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?2004l"))
+    }
+}
+
+/// Begin a synchronized update, i.e. `CSI ? 2026 h`, so that the terminal holds rendering until
+/// the update ends, which avoids tearing.
+///
+/// Nothing is written for terminals that do not support synchronized output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BeginSynchronizedUpdate;
+
+impl Command for BeginSynchronizedUpdate {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?2026h"))
+    }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if capabilities.synchronized_output {
+            self.write(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// End a synchronized update, i.e. `CSI ? 2026 l`, see [`BeginSynchronizedUpdate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EndSynchronizedUpdate;
+
+impl Command for EndSynchronizedUpdate {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?2026l"))
+    }
+
+    fn write_with(
+        &self,
+        writer: &mut impl fmt::Write,
+        capabilities: &TerminalCapabilities,
+    ) -> fmt::Result {
+        if capabilities.synchronized_output {
+            self.write(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// Query the size of the text area in characters, i.e. `CSI 18 t`.
+///
+/// The reply is reported as [`ScreenEvent::Resized`](crate::events::ScreenEvent::Resized).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QuerySize;
+
+impl Command for QuerySize {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("18t"))
+    }
+}
+
+/// Request the primary device attributes, i.e. `CSI c`, that is the conformance level of the
+/// terminal along with its supported extensions.
+///
+/// The reply is reported as
+/// [`TerminalResponse::PrimaryDeviceAttributes`](crate::events::TerminalResponse::PrimaryDeviceAttributes).
+/// Virtually any terminal answers it, so that it may also serve as a barrier after other queries.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RequestPrimaryDeviceAttributes;
+
+impl Command for RequestPrimaryDeviceAttributes {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("c"))
+    }
+}
+
+/// Request the secondary device attributes, i.e. `CSI > c`, that is the type and the firmware
+/// version of the terminal.
+///
+/// The reply is reported as
+/// [`TerminalResponse::SecondaryDeviceAttributes`](crate::events::TerminalResponse::SecondaryDeviceAttributes).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RequestSecondaryDeviceAttributes;
+
+impl Command for RequestSecondaryDeviceAttributes {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!(">c"))
+    }
+}
+
+/// Request the name and version of the terminal, i.e. `CSI > q` (XTVERSION).
+///
+/// The reply is reported as
+/// [`TerminalResponse::Version`](crate::events::TerminalResponse::Version). Terminals that do
+/// not support it do not answer at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RequestTerminalVersion;
+
+impl Command for RequestTerminalVersion {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!(">q"))
+    }
+}
+
+/// Query the default foreground color, i.e. `OSC 10 ; ? ST`.
+///
+/// The reply is reported as [`ColorEvent::Foreground`](crate::events::ColorEvent::Foreground).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QueryForegroundColor;
+
+impl Command for QueryForegroundColor {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, osc!("10;?"))
+    }
+}
+
+/// Query the default background color, i.e. `OSC 11 ; ? ST`.
+///
+/// The reply is reported as [`ColorEvent::Background`](crate::events::ColorEvent::Background),
+/// see [`ColorScheme::from_background`](crate::style::ColorScheme::from_background) to tell dark
+/// and light backgrounds apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QueryBackgroundColor;
+
+impl Command for QueryBackgroundColor {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, osc!("11;?"))
+    }
+}
+
+/// Query the value of the given palette color, i.e. `OSC 4 ; index ; ? ST`.
+///
+/// The reply is reported as [`ColorEvent::Palette`](crate::events::ColorEvent::Palette).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct QueryPaletteColor(pub u8);
+
+impl Command for QueryPaletteColor {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, osc!("4;{};?"), self.0)
+    }
+}
+
+/// Request the state of a mode (DECRQM), i.e. `CSI ? mode $ p` for a DEC private mode or
+/// `CSI mode $ p` for an ANSI mode, e.g. `RequestMode(2004, true)` for bracketed paste.
+///
+/// The reply is reported as [`ModeReport`](crate::events::ModeReport). Terminals that do not
+/// support it do not answer at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RequestMode(pub u16, pub bool);
+
+impl RequestMode {
+    pub fn private(mode: u16) -> Self {
+        Self(mode, true)
+    }
+
+    pub fn ansi(mode: u16) -> Self {
+        Self(mode, false)
+    }
+}
+
+impl Command for RequestMode {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        if self.1 {
+            write!(writer, csi!("?{}$p"), self.0)
+        } else {
+            write!(writer, csi!("{}$p"), self.0)
+        }
+    }
+}
+
+/// Enable in-band resize notifications, i.e. DEC private mode 2048, so that the terminal reports
+/// its size on every resize as [`ScreenEvent::Resized`](crate::events::ScreenEvent::Resized),
+/// including right away.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EnableInBandResize;
+
+impl Command for EnableInBandResize {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?2048h"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DisableInBandResize;
+
+impl Command for DisableInBandResize {
+    fn write(&self, writer: &mut impl fmt::Write) -> fmt::Result {
+        write!(writer, csi!("?2048l"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[cfg(not(feature = "std"))]
@@ -157,10 +484,30 @@ mod tests {
 
     use speculoos::prelude::*;
 
-    use crate::Executable;
+    use crate::cursor::{CursorStyle, SetCursorStyle};
+    use crate::style::{
+        Color, ColorDepth, Colors, SetBackgroundColor, SetColors, SetForegroundColor,
+    };
+    use crate::{Executable, Queuable};
 
     use super::*;
 
+    #[test]
+    fn it_should_convert_io_error_into_error() {
+        let error = Error::from(io::Error::TimedOut);
+
+        assert_that!(error).is_equal_to(Error::Io(io::Error::TimedOut));
+        assert_that!(error.to_string().as_str()).is_equal_to("i/o error: operation timed out");
+    }
+
+    #[test]
+    fn it_should_convert_format_error_into_error() {
+        let error = Error::from(fmt::Error);
+
+        assert_that!(error).is_equal_to(Error::Format);
+        assert_that!(error.to_string().as_str()).is_equal_to("formatting error");
+    }
+
     #[test]
     fn it_should_write_clear_all_action() {
         let action = Clear(ClearType::All);
@@ -180,4 +527,230 @@ mod tests {
         assert_that!(result).is_ok();
         assert_that!(buffer.as_str()).is_equal_to(csi!("32S"));
     }
+
+    #[test]
+    fn it_should_write_enable_bracketed_paste_action() {
+        let action = EnableBracketedPaste;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("?2004h"));
+    }
+
+    #[test]
+    fn it_should_write_disable_bracketed_paste_action() {
+        let action = DisableBracketedPaste;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("?2004l"));
+    }
+
+    #[test]
+    fn it_should_write_synchronized_update_actions() {
+        let mut buffer = String::default();
+
+        let result = buffer
+            .queue(BeginSynchronizedUpdate)
+            .and_then(|buffer| buffer.execute(EndSynchronizedUpdate));
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(concat!(csi!("?2026h"), csi!("?2026l")));
+    }
+
+    #[test]
+    fn it_should_skip_synchronized_update_without_support() {
+        let mut buffer = String::default();
+        let mut terminal =
+            Terminal::new(&mut buffer).with_capabilities(TerminalCapabilities::XTERM);
+
+        let result = terminal
+            .queue(BeginSynchronizedUpdate)
+            .and_then(|terminal| terminal.execute(EndSynchronizedUpdate));
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_empty();
+    }
+
+    #[test]
+    fn it_should_write_query_size_action() {
+        let action = QuerySize;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("18t"));
+    }
+
+    #[test]
+    fn it_should_write_request_primary_device_attributes_action() {
+        let action = RequestPrimaryDeviceAttributes;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("c"));
+    }
+
+    #[test]
+    fn it_should_write_request_secondary_device_attributes_action() {
+        let action = RequestSecondaryDeviceAttributes;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!(">c"));
+    }
+
+    #[test]
+    fn it_should_write_request_terminal_version_action() {
+        let action = RequestTerminalVersion;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!(">q"));
+    }
+
+    #[test]
+    fn it_should_write_query_foreground_color_action() {
+        let action = QueryForegroundColor;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(osc!("10;?"));
+    }
+
+    #[test]
+    fn it_should_write_query_background_color_action() {
+        let action = QueryBackgroundColor;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(osc!("11;?"));
+    }
+
+    #[test]
+    fn it_should_write_query_palette_color_action() {
+        let action = QueryPaletteColor(4);
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to("\x1b]4;4;?\x1b\\");
+    }
+
+    #[test]
+    fn it_should_write_request_private_mode_action() {
+        let action = RequestMode::private(2026);
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("?2026$p"));
+    }
+
+    #[test]
+    fn it_should_write_request_ansi_mode_action() {
+        let action = RequestMode::ansi(4);
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("4$p"));
+    }
+
+    #[test]
+    fn it_should_write_enable_in_band_resize_action() {
+        let action = EnableInBandResize;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("?2048h"));
+    }
+
+    #[test]
+    fn it_should_write_disable_in_band_resize_action() {
+        let action = DisableInBandResize;
+        let mut buffer = String::default();
+
+        let result = buffer.execute(action);
+        assert_that!(result).is_ok();
+        assert_that!(buffer.as_str()).is_equal_to(csi!("?2048l"));
+    }
+
+    #[test]
+    fn it_should_degrade_unsupported_commands_on_vt100() {
+        let mut buffer = String::default();
+        let mut terminal =
+            Terminal::new(&mut buffer).with_capabilities(TerminalCapabilities::VT100);
+
+        let result = terminal
+            .queue(SetForegroundColor(Color::Rgb { r: 1, g: 2, b: 3 }))
+            .and_then(|terminal| terminal.queue(SetCursorStyle(CursorStyle::SteadyBar)))
+            .and_then(|terminal| terminal.queue(Clear(ClearType::History)))
+            .and_then(|terminal| terminal.execute(Clear(ClearType::All)));
+        assert_that!(result).is_ok();
+
+        assert_that!(buffer.as_str()).is_equal_to(concat!(csi!("22m"), csi!("2J")));
+    }
+
+    #[test]
+    fn it_should_downsample_colors_to_terminal_color_depth() {
+        let mut buffer = String::default();
+        let capabilities = TerminalCapabilities::XTERM.with_color_depth(ColorDepth::Ansi);
+        let mut terminal = Terminal::new(&mut buffer).with_capabilities(capabilities);
+
+        let result = terminal.execute(SetColors(Colors {
+            fg: Some(Color::Rgb { r: 250, g: 5, b: 5 }),
+            bg: Some(Color::Ansi(0)),
+            ul: Some(Color::DarkRed),
+        }));
+        assert_that!(result).is_ok();
+
+        assert_that!(buffer.as_str()).is_equal_to(csi!("91;40m"));
+    }
+
+    #[test]
+    fn it_should_not_leave_attributes_on_after_monochrome_colors() {
+        let mut buffer = String::default();
+        let mut terminal =
+            Terminal::new(&mut buffer).with_capabilities(TerminalCapabilities::VT100);
+
+        let result = terminal
+            .queue(SetForegroundColor(Color::White))
+            .and_then(|terminal| terminal.queue(SetBackgroundColor(Color::White)))
+            .and_then(|terminal| terminal.queue(SetForegroundColor(Color::Reset)))
+            .and_then(|terminal| terminal.execute(SetBackgroundColor(Color::Reset)));
+        assert_that!(result).is_ok();
+
+        assert_that!(buffer.as_str()).is_equal_to(concat!(
+            csi!("1m"),
+            csi!("7m"),
+            csi!("22m"),
+            csi!("27m")
+        ));
+    }
+
+    #[test]
+    fn it_should_write_supported_commands_as_is() {
+        let mut buffer = String::default();
+        let mut terminal =
+            Terminal::new(&mut buffer).with_capabilities(TerminalCapabilities::XTERM);
+
+        let result = terminal
+            .queue(SetForegroundColor(Color::Rgb { r: 1, g: 2, b: 3 }))
+            .and_then(|terminal| terminal.queue(SetCursorStyle(CursorStyle::SteadyBar)))
+            .and_then(|terminal| terminal.execute(Clear(ClearType::History)));
+        assert_that!(result).is_ok();
+
+        assert_that!(buffer.as_str()).is_equal_to(concat!(
+            csi!("38;2;1;2;3m"),
+            csi!("6q"),
+            csi!("3J")
+        ));
+    }
 }
@@ -0,0 +1,451 @@
+//! Capabilities.
+//!
+//! Detection of what a terminal supports, either by querying it or from built-in profiles of
+//! well-known terminals, so that commands are only emitted if they are actually understood.
+
+use core::ops::ControlFlow;
+use core::time::Duration;
+
+use crate::Queuable;
+use crate::events::{AsyncEventReader, Event, ModeState, TerminalResponse};
+use crate::io;
+use crate::keyboard::QueryKeyboardEnhancementFlags;
+use crate::style::ColorDepth;
+use crate::terminal::{
+    RequestMode, RequestPrimaryDeviceAttributes, RequestSecondaryDeviceAttributes,
+    RequestTerminalVersion,
+};
+use crate::time::Delay;
+
+/// Synchronized output mode, i.e. `CSI ? 2026 h`.
+const SYNCHRONIZED_OUTPUT_MODE: u16 = 2026;
+
+/// SGR mouse mode, i.e. `CSI ? 1006 h`.
+const SGR_MOUSE_MODE: u16 = 1006;
+
+/// Features supported by a terminal.
+///
+/// The default value stands for a dumb terminal, which supports none of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TerminalCapabilities {
+    /// The 16 named colors.
+    pub ansi_colors: bool,
+    /// The 256 colors of the xterm palette.
+    pub colors_256: bool,
+    /// 24-bit RGB colors.
+    pub truecolor: bool,
+    /// The [kitty keyboard protocol](https://sw.kovidgoyal.net/kitty/keyboard-protocol/).
+    pub kitty_keyboard: bool,
+    /// Synchronized output, i.e. DEC private mode 2026.
+    pub synchronized_output: bool,
+    /// SGR mouse reporting, i.e. DEC private mode 1006.
+    pub sgr_mouse: bool,
+    /// Hyperlinks, i.e. `OSC 8`.
+    pub hyperlinks: bool,
+    /// Inline images, either with sixel or the kitty graphics protocol.
+    pub graphics: bool,
+    /// Cursor styles, i.e. `CSI Ps SP q` (DECSCUSR).
+    pub cursor_style: bool,
+    /// Clearing the scrollback buffer, i.e. `CSI 3 J`.
+    pub clear_history: bool,
+}
+
+impl TerminalCapabilities {
+    /// DEC VT100 and VT102, which have no colors at all.
+    pub const VT100: Self = Self {
+        ansi_colors: false,
+        colors_256: false,
+        truecolor: false,
+        kitty_keyboard: false,
+        synchronized_output: false,
+        sgr_mouse: false,
+        hyperlinks: false,
+        graphics: false,
+        cursor_style: false,
+        clear_history: false,
+    };
+
+    /// DEC VT220, which has no colors either.
+    pub const VT220: Self = Self::VT100;
+
+    /// Linux virtual console.
+    pub const LINUX: Self = Self {
+        ansi_colors: true,
+        clear_history: true,
+        ..Self::VT100
+    };
+
+    /// Minicom, which emulates a VT102 with ANSI colors.
+    pub const MINICOM: Self = Self {
+        ansi_colors: true,
+        ..Self::VT100
+    };
+
+    /// PuTTY.
+    pub const PUTTY: Self = Self {
+        ansi_colors: true,
+        colors_256: true,
+        truecolor: true,
+        sgr_mouse: true,
+        ..Self::VT100
+    };
+
+    /// xterm.
+    pub const XTERM: Self = Self {
+        ansi_colors: true,
+        colors_256: true,
+        truecolor: true,
+        sgr_mouse: true,
+        cursor_style: true,
+        clear_history: true,
+        ..Self::VT100
+    };
+
+    /// kitty, which supports everything.
+    pub const KITTY: Self = Self {
+        ansi_colors: true,
+        colors_256: true,
+        truecolor: true,
+        kitty_keyboard: true,
+        synchronized_output: true,
+        sgr_mouse: true,
+        hyperlinks: true,
+        graphics: true,
+        cursor_style: true,
+        clear_history: true,
+    };
+
+    /// Get the built-in profile of a terminal from its `TERM` name, e.g. `xterm-256color`.
+    ///
+    /// Variants of a known terminal, i.e. names with a suffix such as `-256color`, share its
+    /// profile.
+    pub fn from_term(term: &str) -> Option<Self> {
+        if term.eq_ignore_ascii_case("xterm-kitty") {
+            return Some(Self::KITTY);
+        }
+
+        let name = term.split('-').next().unwrap_or_default();
+        let is = |expected: &str| name.eq_ignore_ascii_case(expected);
+
+        if is("vt100") || is("vt102") {
+            Some(Self::VT100)
+        } else if is("vt220") {
+            Some(Self::VT220)
+        } else if is("linux") {
+            Some(Self::LINUX)
+        } else if is("minicom") {
+            Some(Self::MINICOM)
+        } else if is("putty") {
+            Some(Self::PUTTY)
+        } else if is("xterm") {
+            Some(Self::XTERM)
+        } else if is("kitty") {
+            Some(Self::KITTY)
+        } else {
+            None
+        }
+    }
+
+    /// Get the number of colors the terminal displays.
+    pub fn color_depth(&self) -> ColorDepth {
+        ColorDepth::from_capabilities(self)
+    }
+
+    /// Set the number of colors the terminal displays, e.g. to force monochrome output.
+    pub fn with_color_depth(mut self, depth: ColorDepth) -> Self {
+        self.truecolor = depth >= ColorDepth::TrueColor;
+        self.colors_256 = depth >= ColorDepth::Palette;
+        self.ansi_colors = depth >= ColorDepth::Ansi;
+        self
+    }
+
+    /// Update the capabilities with a reply to one of the probe queries.
+    ///
+    /// It tells whether the event is such a reply.
+    fn update(&mut self, event: &Event) -> bool {
+        match event {
+            Event::KeyboardEnhancementFlags(_) => self.kitty_keyboard = true,
+            Event::Mode(report) if report.private => {
+                let supported =
+                    report.state.is_recognized() && report.state != ModeState::PermanentlyReset;
+
+                match report.mode {
+                    SYNCHRONIZED_OUTPUT_MODE => self.synchronized_output = supported,
+                    SGR_MOUSE_MODE => self.sgr_mouse = supported,
+                    _ => return false,
+                }
+            }
+            Event::Terminal(TerminalResponse::PrimaryDeviceAttributes { attributes, .. }) => {
+                for attribute in attributes {
+                    match attribute {
+                        4 => self.graphics = true,
+                        22 => self.ansi_colors = true,
+                        _ => {}
+                    }
+                }
+            }
+            Event::Terminal(TerminalResponse::SecondaryDeviceAttributes {
+                terminal_type, ..
+            }) => {
+                // VTE based terminals, e.g. GNOME Terminal, do not answer version requests.
+                if *terminal_type == 65 {
+                    self.truecolor = true;
+                    self.hyperlinks = true;
+                    self.cursor_style = true;
+                    self.clear_history = true;
+                }
+            }
+            Event::Terminal(TerminalResponse::Version(version)) => {
+                self.update_from_version(version)
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /// Update the capabilities with well-known features of the terminal of the given name and
+    /// version, e.g. `XTerm(390)` or `WezTerm 20240203-110809-5046fc22`.
+    fn update_from_version(&mut self, version: &str) {
+        let name = version.split(['(', ' ']).next().unwrap_or_default();
+        let is = |expected: &str| name.eq_ignore_ascii_case(expected);
+
+        if is("kitty") || is("WezTerm") || is("ghostty") {
+            self.truecolor = true;
+            self.hyperlinks = true;
+            self.graphics = true;
+            self.cursor_style = true;
+            self.clear_history = true;
+        } else if is("foot") || is("iTerm2") {
+            self.truecolor = true;
+            self.hyperlinks = true;
+            self.cursor_style = true;
+            self.clear_history = true;
+        } else if is("XTerm") || is("tmux") {
+            self.truecolor = true;
+            self.cursor_style = true;
+            self.clear_history = true;
+        }
+    }
+
+    /// Make implied capabilities explicit, e.g. truecolor implies the 256 colors palette.
+    fn normalize(mut self) -> Self {
+        self.colors_256 |= self.truecolor;
+        self.ansi_colors |= self.colors_256;
+        self
+    }
+}
+
+/// Probe the capabilities of the terminal.
+///
+/// All queries are sent at once followed by a primary device attributes request, which virtually
+/// any terminal answers last, so that the probe takes a single round trip. The timeout bounds the
+/// whole probe and should account for slow links, e.g. about 100 bytes of replies at the
+/// baud rate of a serial line.
+///
+/// Replies are merged into the given baseline, e.g. the profile from
+/// [`TerminalCapabilities::from_term`], as not every capability can be probed: PuTTY for instance
+/// only reports its conformance level, and nothing about colors. Explicit mode reports override
+/// the baseline, while other replies only add capabilities to it. Use the default capabilities as
+/// baseline to rely on the probe alone.
+///
+/// Terminals that do not answer at all within the timeout keep the baseline, in which case late
+/// replies, if any, are eventually read as regular events. The probe also ends with the
+/// capabilities gathered so far once too many unrelated events arrived meanwhile, see
+/// [`AsyncEventReader::query_until`].
+pub async fn probe<ReaderTy, DelayTy, WriterTy, const BUFFER_SIZE: usize, const QUEUE_SIZE: usize>(
+    events: &mut AsyncEventReader<'_, ReaderTy, DelayTy, BUFFER_SIZE, QUEUE_SIZE>,
+    writer: &mut WriterTy,
+    baseline: TerminalCapabilities,
+    timeout: Duration,
+) -> io::Result<TerminalCapabilities>
+where
+    ReaderTy: io::Read,
+    DelayTy: Delay,
+    WriterTy: io::blocking::Write,
+{
+    writer
+        .queue(QueryKeyboardEnhancementFlags)?
+        .queue(RequestMode::private(SYNCHRONIZED_OUTPUT_MODE))?
+        .queue(RequestMode::private(SGR_MOUSE_MODE))?
+        .queue(RequestTerminalVersion)?
+        .queue(RequestSecondaryDeviceAttributes)?;
+
+    let mut capabilities = baseline;
+
+    let result = events
+        .query_until(writer, RequestPrimaryDeviceAttributes, timeout, |event| {
+            let consumed = capabilities.update(event);
+
+            match event {
+                Event::Terminal(TerminalResponse::PrimaryDeviceAttributes { .. }) => {
+                    Some(ControlFlow::Break(()))
+                }
+                _ if consumed => Some(ControlFlow::Continue(())),
+                _ => None,
+            }
+        })
+        .await;
+
+    match result {
+        Ok(()) | Err(io::Error::TimedOut | io::Error::BufferFull) => Ok(capabilities.normalize()),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "std"))]
+    compile_error!("missing feature std to run tests");
+
+    use rstest::rstest;
+    use speculoos::prelude::*;
+
+    use crate::events::{KeyCode, KeyEvent};
+
+    use super::*;
+
+    /// Terminal that sends the given replies, and then nothing at all.
+    struct Replies(Vec<u8>);
+
+    impl io::Read for Replies {
+        async fn read(&mut self, data: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                futures::future::pending::<()>().await;
+            }
+
+            let n = self.0.len().min(data.len());
+            data[..n].copy_from_slice(&self.0[..n]);
+            self.0.drain(..n);
+            Ok(n)
+        }
+    }
+
+    struct Sleep;
+
+    impl Delay for Sleep {
+        async fn delay(&mut self, duration: Duration) {
+            tokio::time::sleep(duration).await
+        }
+    }
+
+    #[rstest]
+    #[case::vt100(
+        b"\x1b[?1;2c",
+        TerminalCapabilities::default(),
+        TerminalCapabilities::default()
+    )]
+    #[case::vt340(
+        b"\x1b[?63;1;2;4;6;22c",
+        TerminalCapabilities::default(),
+        TerminalCapabilities {
+            ansi_colors: true,
+            graphics: true,
+            ..Default::default()
+        }
+    )]
+    #[case::xterm(
+        b"\x1b[?2026;0$y\x1b[?1006;2$y\x1bP>|XTerm(390)\x1b\\\x1b[>41;390;0c\x1b[?64;1;2;6;9;15;16;17;18;21;22;28c",
+        TerminalCapabilities::default(),
+        TerminalCapabilities {
+            ansi_colors: true,
+            colors_256: true,
+            truecolor: true,
+            sgr_mouse: true,
+            cursor_style: true,
+            clear_history: true,
+            ..Default::default()
+        }
+    )]
+    #[case::kitty(
+        b"\x1b[?0u\x1b[?2026;2$y\x1b[?1006;2$y\x1bP>|kitty(0.35.2)\x1b\\\x1b[>1;4000;35c\x1b[?62;c",
+        TerminalCapabilities::default(),
+        TerminalCapabilities::KITTY
+    )]
+    #[case::putty(b"\x1b[?6c", TerminalCapabilities::PUTTY, TerminalCapabilities::PUTTY)]
+    #[case::xterm_without_sgr_mouse(
+        b"\x1b[?1006;0$y\x1b[?1;2c",
+        TerminalCapabilities::XTERM,
+        TerminalCapabilities {
+            sgr_mouse: false,
+            ..TerminalCapabilities::XTERM
+        }
+    )]
+    #[case::silent(b"", TerminalCapabilities::default(), TerminalCapabilities::default())]
+    #[case::silent_putty(b"", TerminalCapabilities::PUTTY, TerminalCapabilities::PUTTY)]
+    #[tokio::test]
+    async fn it_should_probe_capabilities(
+        #[case] replies: &[u8],
+        #[case] baseline: TerminalCapabilities,
+        #[case] expected: TerminalCapabilities,
+    ) {
+        let mut input = Replies(replies.to_vec());
+        let mut output = Vec::new();
+
+        let mut events = AsyncEventReader::new(&mut input, Sleep);
+
+        assert_that!(
+            probe(
+                &mut events,
+                &mut output,
+                baseline,
+                Duration::from_millis(50)
+            )
+            .await
+        )
+        .is_ok_containing(expected);
+
+        assert_that!(output)
+            .is_equal_to(b"\x1b[?u\x1b[?2026$p\x1b[?1006$p\x1b[>q\x1b[>c\x1b[c".to_vec());
+    }
+
+    #[rstest]
+    #[case::vt100("vt100", Some(TerminalCapabilities::VT100))]
+    #[case::vt102("vt102", Some(TerminalCapabilities::VT100))]
+    #[case::vt220("vt220", Some(TerminalCapabilities::VT220))]
+    #[case::linux("linux", Some(TerminalCapabilities::LINUX))]
+    #[case::minicom("minicom", Some(TerminalCapabilities::MINICOM))]
+    #[case::putty("putty-256color", Some(TerminalCapabilities::PUTTY))]
+    #[case::xterm("xterm", Some(TerminalCapabilities::XTERM))]
+    #[case::xterm_256color("xterm-256color", Some(TerminalCapabilities::XTERM))]
+    #[case::kitty("xterm-kitty", Some(TerminalCapabilities::KITTY))]
+    #[case::unknown("dumb", None)]
+    fn it_should_get_profile_from_term(
+        #[case] term: &str,
+        #[case] expected: Option<TerminalCapabilities>,
+    ) {
+        assert_that!(TerminalCapabilities::from_term(term)).is_equal_to(expected);
+    }
+
+    #[tokio::test]
+    async fn it_should_keep_other_events_while_probing() {
+        let mut input = Replies(b"a\x1b[?1;2cb".to_vec());
+        let mut output = Vec::new();
+
+        let mut events = AsyncEventReader::new(&mut input, Sleep);
+
+        let baseline = TerminalCapabilities::default();
+
+        assert_that!(
+            probe(
+                &mut events,
+                &mut output,
+                baseline,
+                Duration::from_millis(50)
+            )
+            .await
+        )
+        .is_ok();
+
+        assert_that!(events.next_event().await)
+            .is_some()
+            .is_ok()
+            .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('a'))));
+
+        assert_that!(events.next_event().await)
+            .is_some()
+            .is_ok()
+            .is_equal_to(Event::Key(KeyEvent::from(KeyCode::Char('b'))));
+    }
+}
@@ -0,0 +1,31 @@
+//! Time.
+
+#![allow(async_fn_in_trait)]
+
+use core::time::Duration;
+
+/// Delay trait.
+///
+/// It could be implemented on top of any async timer, e.g. `embassy_time::Timer::after` or
+/// `tokio::time::sleep`.
+pub trait Delay {
+    async fn delay(&mut self, duration: Duration);
+}
+
+impl<DelayTy: Delay> Delay for &mut DelayTy {
+    #[inline]
+    async fn delay(&mut self, duration: Duration) {
+        DelayTy::delay(*self, duration).await
+    }
+}
+
+/// Delay that never elapses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Never;
+
+impl Delay for Never {
+    async fn delay(&mut self, _duration: Duration) {
+        futures::future::pending::<()>().await
+    }
+}